@@ -77,12 +77,12 @@ async function test() {
     let nft_manager_account = await nft_mint.createAccount(sale_manager)
     let manager_pot = await tokenMint.createAccount(sale_manager)
     await pool_api.sellNft(
-        conn,creator,pool.publicKey,nft_mint.publicKey,nft_seller_account,nft_manager_account,manager_pot,100,
+        conn,creator,pool.publicKey,nft_mint.publicKey,nft_seller_account,nft_manager_account,manager_pot,100,0,null,null,
     )
 
     let nft_bidder_account = await nft_mint.createAccount(bidder.publicKey)
     await pool_api.buyNft(
-        conn,bidder,pool.publicKey,nft_mint.publicKey,nft_bidder_account,bidder_token,
+        conn,bidder,pool.publicKey,nft_mint.publicKey,nft_bidder_account,bidder_token,100,100,
     )
     await displayStates(conn,[creator_token,bidder_token])
     // // await pool_api.redeemNft(
@@ -99,11 +99,14 @@ async function test() {
 
     ////////////////////////////////    Auction      ///////////////////////////////////////
     await pool_api.sellNftByAuction(
-        conn,bidder,pool.publicKey,nft_mint.publicKey,nft_bidder_account,nft_manager_account,manager_pot,100,30
+        conn,bidder,pool.publicKey,nft_mint.publicKey,nft_bidder_account,nft_manager_account,manager_pot,100,0,30,0,0,0,null,10,0,
     )
 
+    await pool_api.initBidReceipt(
+        conn,creator,pool.publicKey,nft_mint.publicKey,
+    )
     await pool_api.placeBid(
-        conn,creator,pool.publicKey,nft_mint.publicKey,creator_token,120,
+        conn,creator,pool.publicKey,nft_mint.publicKey,creator_token,120,120,100,120,
     )
     await displayStates(conn,[creator_token,bidder_token])
 }
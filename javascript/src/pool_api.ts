@@ -138,7 +138,10 @@ export async function sellNft(
     nft_seller_token : PublicKey,
     nft_manager_token : PublicKey,
     manager_pot : PublicKey,
-    price : number
+    price : number,
+    starts_at : number,
+    expires_at : number | null,
+    reserved_buyer : PublicKey | null,
     ){
     console.log("+ sellNft")
     let wallet = new anchor.Wallet(owner)
@@ -151,6 +154,9 @@ export async function sellNft(
     try {
         await program.rpc.sellNft(
             new anchor.BN(price),
+            new anchor.BN(starts_at),
+            expires_at==null ? null : new anchor.BN(expires_at),
+            reserved_buyer,
             {
                 accounts:{
                     owner : owner.publicKey,
@@ -162,9 +168,9 @@ export async function sellNft(
                     nftSellerToken : nft_seller_token,
                     nftManagerToken : nft_manager_token,
                     managerPot : manager_pot,
-                    tokenMetadataProgram : metadataProgramId,
                     tokenProgram : splToken.TOKEN_PROGRAM_ID,
                     systemProgram : anchor.web3.SystemProgram.programId,
+                    clockSysvar : SYSVAR_CLOCK_PUBKEY,
                 },
                 signers:[owner,sale_pot]
             }
@@ -183,6 +189,8 @@ export async function buyNft(
     nft_mint : PublicKey,
     nft_bidder_token : PublicKey,
     bidder_token : PublicKey,
+    expected_price : number,
+    max_spend : number,
     ){
     console.log("+ buyNft")
     let wallet = new anchor.Wallet(owner)
@@ -192,8 +200,21 @@ export async function buyNft(
     let metadata = (await PublicKey.findProgramAddress([Buffer.from('metadata'),metadataProgramId.toBuffer(),nft_mint.toBuffer()],metadataProgramId))[0]
     let sale_manager_data = await program.account.saleManager.fetch(sale_manager)
     let sale_pot_data = await program.account.salePot.fetch(sale_manager_data.salePot)
+    let pool_data = await program.account.pool.fetch(pool)
+    // auction listings pass the auction data, english buy-now also the bid history and seller wallet
+    let remaining_accounts = []
+    if(sale_manager_data.isAuctionMode){
+        let auction_data = await program.account.auctionData.fetch(sale_manager_data.auctionData)
+        remaining_accounts.push({pubkey : sale_manager_data.auctionData, isWritable : true, isSigner : false})
+        if(auction_data.auctionType==0){
+            remaining_accounts.push({pubkey : auction_data.bidHistory, isWritable : true, isSigner : false})
+            remaining_accounts.push({pubkey : sale_manager_data.seller, isWritable : true, isSigner : false})
+        }
+    }
     try {
         await program.rpc.buyNft(
+            new anchor.BN(expected_price),
+            new anchor.BN(max_spend),
             {
                 accounts:{
                     owner : owner.publicKey,
@@ -206,9 +227,11 @@ export async function buyNft(
                     nftBidderToken : nft_bidder_token,
                     managerPot : sale_pot_data.poolPot,
                     bidderToken : bidder_token,
+                    feePot : pool_data.feePot,
                     tokenMetadataProgram : metadataProgramId,
                     tokenProgram : splToken.TOKEN_PROGRAM_ID,
                 },
+                remainingAccounts : remaining_accounts,
                 signers:[owner]
             }
         )
@@ -305,7 +328,14 @@ export async function sellNftByAuction(
     nft_manager_token : PublicKey,
     manager_pot : PublicKey,
     price : number,
+    starts_at : number,
     ended_at : number,
+    extension_window : number,
+    extension : number,
+    reserve_price : number,
+    buy_now_price : number | null,
+    gap_tick_percentage : number,
+    min_increment : number,
     ){
     console.log("+ sellNftByAuction")
     let wallet = new anchor.Wallet(owner)
@@ -315,28 +345,39 @@ export async function sellNftByAuction(
     let metadata = (await PublicKey.findProgramAddress([Buffer.from('metadata'),metadataProgramId.toBuffer(),nft_mint.toBuffer()],metadataProgramId))[0]
     let sale_pot = Keypair.generate()
     let auction_data = Keypair.generate()
+    let bid_history = Keypair.generate()
     try {
         await program.rpc.sellNftByAuction(
             new anchor.BN(price),
+            new anchor.BN(starts_at),
             new anchor.BN(ended_at),
+            new anchor.BN(extension_window),
+            new anchor.BN(extension),
+            new anchor.BN(reserve_price),
+            buy_now_price==null ? null : new anchor.BN(buy_now_price),
+            gap_tick_percentage,
+            new anchor.BN(min_increment),
             {
                 accounts:{
-                    owner : owner.publicKey,
-                    pool : pool,
-                    nftMint : nft_mint,
-                    metadata : metadata,
-                    saleManager : sale_manager,
-                    auctionData : auction_data.publicKey,
-                    salePot : sale_pot.publicKey,
-                    nftSellerToken : nft_seller_token,
-                    nftManagerToken : nft_manager_token,
-                    managerPot : manager_pot,
-                    tokenMetadataProgram : metadataProgramId,
-                    tokenProgram : splToken.TOKEN_PROGRAM_ID,
+                    listing : {
+                        owner : owner.publicKey,
+                        pool : pool,
+                        nftMint : nft_mint,
+                        metadata : metadata,
+                        saleManager : sale_manager,
+                        auctionData : auction_data.publicKey,
+                        salePot : sale_pot.publicKey,
+                        nftSellerToken : nft_seller_token,
+                        nftManagerToken : nft_manager_token,
+                        managerPot : manager_pot,
+                        tokenProgram : splToken.TOKEN_PROGRAM_ID,
+                        systemProgram : anchor.web3.SystemProgram.programId,
+                        clockSysvar : SYSVAR_CLOCK_PUBKEY,
+                    },
                     systemProgram : anchor.web3.SystemProgram.programId,
-                    clockSysvar : SYSVAR_CLOCK_PUBKEY,
+                    bidHistory : bid_history.publicKey,
                 },
-                signers:[owner,auction_data,sale_pot]
+                signers:[owner,auction_data,sale_pot,bid_history]
             }
         )
     } catch(err){
@@ -349,6 +390,40 @@ export async function sellNftByAuction(
     // console.log(account) 
 }
 
+export async function initBidReceipt(
+    conn : Connection,
+    owner : Keypair,
+    pool : PublicKey,
+    nft_mint : PublicKey,
+    ){
+    console.log("+ initBidReceipt")
+    let wallet = new anchor.Wallet(owner)
+    let provider = new anchor.Provider(conn,wallet,anchor.Provider.defaultOptions())
+    const program = new anchor.Program(idl,programId,provider)
+    let sale_manager = (await PublicKey.findProgramAddress([pool.toBuffer(),nft_mint.toBuffer()],programId))[0]
+    let sale_manager_data = await program.account.saleManager.fetch(sale_manager)
+    let [bid_receipt,bump] = (await PublicKey.findProgramAddress([sale_manager_data.auctionData.toBuffer(),owner.publicKey.toBuffer()],programId))
+    try {
+        await program.rpc.initBidReceipt(
+            new anchor.BN(bump),
+            {
+                accounts:{
+                    owner : owner.publicKey,
+                    saleManager : sale_manager,
+                    auctionData : sale_manager_data.auctionData,
+                    bidReceipt : bid_receipt,
+                    systemProgram : anchor.web3.SystemProgram.programId,
+                },
+                signers:[owner]
+            }
+        )
+    } catch(err){
+        console.log(err)
+    }
+    console.log("- end")
+    await sleep(1000)
+}
+
 export async function placeBid(
     conn : Connection,
     owner : Keypair,
@@ -356,6 +431,9 @@ export async function placeBid(
     nft_mint : PublicKey,
     bidder_token : PublicKey,
     price : number,
+    max_price : number,
+    expected_price : number,
+    max_spend : number,
     ){
     console.log("+ placeBid")
     let wallet = new anchor.Wallet(owner)
@@ -365,9 +443,13 @@ export async function placeBid(
     let sale_manager_data = await program.account.saleManager.fetch(sale_manager)
     let sale_pot_data = await program.account.salePot.fetch(sale_manager_data.salePot)
     let auction_data = await program.account.auctionData.fetch(sale_manager_data.auctionData)
+    let bid_receipt = (await PublicKey.findProgramAddress([sale_manager_data.auctionData.toBuffer(),owner.publicKey.toBuffer()],programId))[0]
     try {
         await program.rpc.placeBid(
             new anchor.BN(price),
+            new anchor.BN(max_price),
+            new anchor.BN(expected_price),
+            new anchor.BN(max_spend),
             {
                 accounts:{
                     owner : owner.publicKey,
@@ -377,7 +459,8 @@ export async function placeBid(
                     auctionData : sale_manager_data.auctionData,
                     managerPot : sale_pot_data.poolPot,
                     bidderToken : bidder_token,
-                    prevBidderToken : auction_data.auctionState==1 ? bidder_token : auction_data.lastBidderToken,
+                    bidReceipt : bid_receipt,
+                    bidHistory : auction_data.bidHistory,
                     tokenMetadataProgram : metadataProgramId,
                     tokenProgram : splToken.TOKEN_PROGRAM_ID,
                     clockSysvar : SYSVAR_CLOCK_PUBKEY,
@@ -410,18 +493,24 @@ export async function claimBid(
     let sale_manager_data = await program.account.saleManager.fetch(sale_manager)
     let sale_pot_data = await program.account.salePot.fetch(sale_manager_data.salePot) 
     let auction_data = await program.account.auctionData.fetch(sale_manager_data.auctionData)
+    let pool_data = await program.account.pool.fetch(pool)
     try {
         await program.rpc.claimBid(
             {
                 accounts:{
                     owner : owner.publicKey,
+                    pool : pool,
                     nftMint : nft_mint,
                     metadata : metadata,
                     saleManager : sale_manager,
                     salePot : sale_manager_data.salePot,
                     auctionData : sale_manager_data.auctionData,
+                    bidHistory : auction_data.bidHistory,
+                    seller : sale_manager_data.seller,
                     nftManagerToken : sale_manager_data.nftPot,
                     nftBidderToken : nft_bidder_token,
+                    managerPot : sale_pot_data.poolPot,
+                    feePot : pool_data.feePot,
                     tokenMetadataProgram : metadataProgramId,
                     tokenProgram : splToken.TOKEN_PROGRAM_ID,
                     clockSysvar : SYSVAR_CLOCK_PUBKEY,
@@ -1,32 +1,63 @@
 pub mod utils;
+pub mod payout;
+pub mod pricing;
 use borsh::{BorshDeserialize,BorshSerialize};
 use {
     crate::utils::*,
+    crate::payout::*,
+    crate::pricing::*,
     anchor_lang::{
         prelude::*,
         AnchorDeserialize,
         AnchorSerialize,
         Key,
+        AccountsClose,
         solana_program::{
             program::{invoke,invoke_signed},
+            system_instruction,
             program_pack::Pack,
             sysvar::{clock::Clock},
+            keccak::hashv,
+            program_option::COption,
         }      
     },
     metaplex_token_metadata::{
-        instruction::{create_metadata_accounts,create_master_edition,update_metadata_accounts,update_primary_sale_happened_via_token},
+        instruction::{create_metadata_accounts,create_master_edition,update_primary_sale_happened_via_token},
     },
     spl_token::state,
 };
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
-pub const POOL_SIZE : usize = 32+1+32;
+// account sizes grew past the original layout and old accounts are not migrated, see Read me
+pub const POOL_SIZE : usize = 32+1+32+2+32+1+8+8+1+8;
 pub const MAX_CREATOR_NUM : usize = 6;
 pub const CREATOR_SIZE : usize = 32+1+1;
-pub const MAX_SALE_MANAGER_SIZE : usize = 32+32+32+32+32+8+1+1+1+32+1;
-pub const SALE_POT_SIZE : usize = 1+32+32+8+1+32+1+2+CREATOR_SIZE*MAX_CREATOR_NUM;
-pub const AUCTION_DATA_SIZE : usize = 8+32+32+1+1;
+pub const MAX_SALE_MANAGER_SIZE : usize = 32+32+32+32+32+8+1+1+1+32+1+8+8+32+32+1;
+pub const SALE_POT_SIZE : usize = 1+32+32+8+1+32+1+2+8+4+CREATOR_SIZE*MAX_CREATOR_NUM;
+pub const MAX_BUNDLE_ITEM_NUM : usize = 4;
+pub const BUNDLE_ITEM_SIZE : usize = 32+32+32;
+pub const BUNDLE_SIZE : usize = 32+4+BUNDLE_ITEM_SIZE*MAX_BUNDLE_ITEM_NUM;
+// remaining accounts per bundle item : sale pot, metadata, nft pot, destination token
+pub const BUNDLE_ITEM_ACCOUNT_NUM : usize = 4;
+pub const AUCTION_DATA_SIZE : usize = 8+32+32+1+1+1+8+8+8+8+8+8+8+8+8+4+8+8+32+8+8+32+32;
+pub const SEALED_BID_SIZE : usize = 32+32+32+32+8+8+1+1;
+pub const BID_RECEIPT_SIZE : usize = 32+32+32+8+1;
+pub const OFFER_SIZE : usize = 32+32+1+32+32+8+1;
+pub const MAX_BID_HISTORY_NUM : usize = 32;
+pub const BID_RECORD_SIZE : usize = 32+8+8;
+pub const BID_HISTORY_SIZE : usize = 32+4+4+BID_RECORD_SIZE*MAX_BID_HISTORY_NUM;
 pub const PREFIX : &str = "auction";
+pub const TREASURY : &str = "treasury";
+pub const MAX_FEE_BASIS_POINTS : u16 = 1000;
+pub const DEFAULT_MAX_GAP_TICK_PERCENTAGE : u8 = 10;
+pub const MAX_GAP_TICK_PERCENTAGE : u8 = 100;
+pub const AUCTION_TYPE_ENGLISH : u8 = 0;
+pub const AUCTION_TYPE_DUTCH : u8 = 1;
+pub const AUCTION_TYPE_SEALED_FIRST_PRICE : u8 = 2;
+pub const AUCTION_TYPE_SEALED_SECOND_PRICE : u8 = 3;
+pub const OFFER_SCOPE_MINT : u8 = 0;
+pub const OFFER_SCOPE_CREATOR : u8 = 1;
+pub const OFFER_SCOPE_COLLECTION : u8 = 2;
 //sell
 //buy
 //redeem
@@ -40,9 +71,11 @@ pub mod solana_anchor {
         ctx : Context<InitPool>,
         ) -> ProgramResult {
         let pool = &mut ctx.accounts.pool;
-        let sale_mint : state::Mint = state::Mint::unpack_from_slice(&ctx.accounts.sale_mint.data.borrow())?;
+        // fails unless sale_mint is an initialized spl mint
+        state::Mint::unpack_from_slice(&ctx.accounts.sale_mint.data.borrow())?;
         pool.owner = *ctx.accounts.owner.key;
         pool.sale_mint = *ctx.accounts.sale_mint.key;
+        pool.max_gap_tick_percentage = DEFAULT_MAX_GAP_TICK_PERCENTAGE;
         Ok(())
     }
 
@@ -54,6 +87,91 @@ pub mod solana_anchor {
         Ok(())
     }
 
+    pub fn set_fee(
+        ctx : Context<SetFee>,
+        _fee_basis_points : u16,
+        _bump : u8,
+        ) -> ProgramResult {
+        let pool = &mut ctx.accounts.pool;
+        let fee_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.fee_pot.data.borrow())?;
+        if _fee_basis_points > MAX_FEE_BASIS_POINTS {
+            return Err(PoolError::InvalidFee.into());
+        }
+        if fee_pot.mint != pool.sale_mint {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if fee_pot.owner != *ctx.accounts.treasury.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        pool.fee_basis_points = _fee_basis_points;
+        pool.fee_pot = *ctx.accounts.fee_pot.key;
+        pool.treasury_bump = _bump;
+        Ok(())
+    }
+
+    pub fn set_extension_limits(
+        ctx : Context<SetPoolLimits>,
+        _max_extension_window : i64,
+        _max_extension : i64,
+        ) -> ProgramResult {
+        let pool = &mut ctx.accounts.pool;
+        if _max_extension_window < 0 || _max_extension < 0 {
+            return Err(PoolError::InvalidExtension.into());
+        }
+        pool.max_extension_window = _max_extension_window;
+        pool.max_extension = _max_extension;
+        Ok(())
+    }
+
+    pub fn set_bid_increment_limits(
+        ctx : Context<SetPoolLimits>,
+        _max_gap_tick_percentage : u8,
+        _max_min_increment : u64,
+        ) -> ProgramResult {
+        let pool = &mut ctx.accounts.pool;
+        // zero turns a mode off, but sellers need at least one mode to choose
+        if _max_gap_tick_percentage > MAX_GAP_TICK_PERCENTAGE || (_max_gap_tick_percentage == 0 && _max_min_increment == 0) {
+            return Err(PoolError::InvalidBidIncrement.into());
+        }
+        pool.max_gap_tick_percentage = _max_gap_tick_percentage;
+        pool.max_min_increment = _max_min_increment;
+        Ok(())
+    }
+
+    pub fn withdraw_fee(
+        ctx : Context<WithdrawFee>,
+        ) -> ProgramResult {
+        let pool = &ctx.accounts.pool;
+        let pool_key = pool.key();
+        let fee_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.fee_pot.data.borrow())?;
+        let withdraw_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.withdraw_pot.data.borrow())?;
+        if *ctx.accounts.fee_pot.key != pool.fee_pot {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if withdraw_pot.mint != pool.sale_mint {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if fee_pot.amount == 0 {
+            return Err(PoolError::InvalidAmount.into());
+        }
+        let treasury_seeds = &[
+            pool_key.as_ref(),
+            TREASURY.as_bytes(),
+            &[pool.treasury_bump]
+        ];
+        spl_token_transfer(
+            TokenTransferParams{
+                source : ctx.accounts.fee_pot.clone(),
+                destination : ctx.accounts.withdraw_pot.clone(),
+                amount : fee_pot.amount,
+                authority : ctx.accounts.treasury.clone(),
+                authority_signer_seeds : treasury_seeds,
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+        Ok(())
+    }
+
     pub fn mint_nft(
         ctx : Context<MintNft>,
         _data : Metadata,
@@ -76,7 +194,7 @@ pub mod solana_anchor {
                 account : ctx.accounts.token_account.clone(),
                 owner : ctx.accounts.owner.clone(),
                 token_program : ctx.accounts.token_program.clone(),
-                amount : 1 as u64,
+                amount : 1,
             }
         )?;
 
@@ -166,17 +284,25 @@ pub mod solana_anchor {
     pub fn sell_nft(
         ctx : Context<SellNft>,
         _price : u64,
+        _starts_at : i64,
+        _expires_at : Option<i64>,
+        _reserved_buyer : Option<Pubkey>,
         ) -> ProgramResult {
         let pool = &ctx.accounts.pool;
 
         let sale_manager_key = ctx.accounts.sale_manager.key();
         let sale_manager = &mut ctx.accounts.sale_manager;
+        // a live listing keeps its seller and nft pot until it is sold, redeemed or expired
+        if sale_manager.sale_state == 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
         sale_manager.sale_pot = ctx.accounts.sale_pot.key();
         let sale_pot = &mut ctx.accounts.sale_pot;
         let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
         let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
         let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
-        let metadata : metaplex_token_metadata::state::Metadata =  metaplex_token_metadata::state::Metadata::from_account_info(&ctx.accounts.metadata)?;
+        let metadata = load_metadata(&ctx.accounts.metadata, ctx.accounts.nft_mint.key)?;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
         if nft_seller_token.owner != *ctx.accounts.owner.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
@@ -192,34 +318,32 @@ pub mod solana_anchor {
         if manager_pot.owner != sale_manager_key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
+        if metadata.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMetadata.into());
+        }
+        let starts_at = _starts_at.max(clock.unix_timestamp);
+        let expires_at = _expires_at.unwrap_or(0);
+        if _expires_at.is_some() && expires_at <= starts_at {
+            return Err(PoolError::InvalidAuctionSchedule.into());
+        }
 
-        sale_manager.seller=*ctx.accounts.owner.key;
+        sale_manager.seller = *ctx.accounts.owner.key;
         sale_manager.price=_price;
         sale_manager.sale_state =1;
         sale_manager.nft_pot = *ctx.accounts.nft_manager_token.key;
         sale_manager.is_auction_mode = false;
+        sale_manager.is_delegated = false;
+        sale_manager.starts_at = starts_at;
+        sale_manager.expires_at = expires_at;
+        sale_manager.reserved_buyer = _reserved_buyer.unwrap_or_default();
 
         // sale_manager.is_primary = metadata.primary_sale_happened;
         sale_pot.sale_manager = sale_manager_key;
         sale_pot.is_used = false;
         sale_pot.price = _price;
-        sale_pot.pool_pot=*ctx.accounts.manager_pot.key;
-        sale_pot.is_primary = !metadata.primary_sale_happened;
-        if sale_pot.is_primary {
-            sale_pot.seller_verified = true;
-        } else {
-            sale_pot.seller_verified = false;
-        }
+        sale_pot.pool_pot = *ctx.accounts.manager_pot.key;
         sale_pot.seller = *ctx.accounts.owner.key;
-        if let Some(creators) = metadata.data.creators{
-            for c in creators {
-                sale_pot.creators.push(Creator{
-                    address : c.address,
-                    verified : false,
-                    share : c.share,
-                })
-            }
-        }
+        set_royalty(sale_pot, &metadata)?;
         spl_token_transfer_without_seed(
             TokenTransferParamsWithoutSeed{
                 source : ctx.accounts.nft_seller_token.clone(),
@@ -229,40 +353,107 @@ pub mod solana_anchor {
                 token_program : ctx.accounts.token_program.clone(),
             }
         )?;
-        invoke(
-            &update_metadata_accounts(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                *ctx.accounts.owner.key,
-                Some(sale_manager_key),
-                None,
-                None,
-            ),
-            &[
-                ctx.accounts.token_metadata_program.clone(),
-                ctx.accounts.metadata.clone(),
-                ctx.accounts.owner.clone(),
-            ]
+        Ok(())
+    }
+
+    pub fn sell_nft_by_delegate(
+        ctx : Context<SellNftByDelegate>,
+        _price : u64,
+        _starts_at : i64,
+        _expires_at : Option<i64>,
+        _reserved_buyer : Option<Pubkey>,
+        ) -> ProgramResult {
+        let pool = &ctx.accounts.pool;
+
+        let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        // a live listing keeps its seller and nft pot until it is sold, redeemed or expired
+        if sale_manager.sale_state == 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        sale_manager.sale_pot = ctx.accounts.sale_pot.key();
+        let sale_pot = &mut ctx.accounts.sale_pot;
+        let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
+        let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
+        let metadata = load_metadata(&ctx.accounts.metadata, ctx.accounts.nft_mint.key)?;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        if nft_seller_token.owner != *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.amount != 1 {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        // only one token of the mint can exist, so no other holder can list it over this one
+        let nft_mint : state::Mint = state::Mint::unpack_from_slice(&ctx.accounts.nft_mint.data.borrow())?;
+        if nft_mint.supply != 1 || nft_mint.decimals != 0 {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if manager_pot.mint != pool.sale_mint {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if manager_pot.owner != sale_manager_key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if metadata.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMetadata.into());
+        }
+        let starts_at = _starts_at.max(clock.unix_timestamp);
+        let expires_at = _expires_at.unwrap_or(0);
+        if _expires_at.is_some() && expires_at <= starts_at {
+            return Err(PoolError::InvalidAuctionSchedule.into());
+        }
+
+        sale_manager.seller = *ctx.accounts.owner.key;
+        sale_manager.price=_price;
+        sale_manager.sale_state =1;
+        // the nft stays in the seller's wallet with the sale manager as delegate
+        sale_manager.nft_pot = *ctx.accounts.nft_seller_token.key;
+        sale_manager.is_auction_mode = false;
+        sale_manager.is_delegated = true;
+        sale_manager.starts_at = starts_at;
+        sale_manager.expires_at = expires_at;
+        sale_manager.reserved_buyer = _reserved_buyer.unwrap_or_default();
+
+        sale_pot.sale_manager = sale_manager_key;
+        sale_pot.is_used = false;
+        sale_pot.price = _price;
+        sale_pot.pool_pot = *ctx.accounts.manager_pot.key;
+        sale_pot.seller = *ctx.accounts.owner.key;
+        set_royalty(sale_pot, &metadata)?;
+        spl_token_approve(
+            TokenApproveParams{
+                source : ctx.accounts.nft_seller_token.clone(),
+                delegate : ctx.accounts.sale_manager.to_account_info().clone(),
+                owner : ctx.accounts.owner.clone(),
+                token_program : ctx.accounts.token_program.clone(),
+                amount : 1,
+            }
         )?;
         Ok(())
     }
 
-    pub fn buy_nft(
-        ctx : Context<BuyNft>,
+    pub fn buy_nft<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, BuyNft<'info>>,
+        _expected_price : u64,
+        _max_spend : u64,
         ) -> ProgramResult {
         let pool = &ctx.accounts.pool;
-        let pool_info = ctx.accounts.pool.to_account_info().clone();
         let sale_manager_info1 = ctx.accounts.sale_manager.to_account_info().clone();
-        let sale_manager_info2 = ctx.accounts.sale_manager.to_account_info().clone();
         let sale_manager_info3 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info4 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info5 = ctx.accounts.sale_manager.to_account_info().clone();
         let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_pot_key = ctx.accounts.sale_pot.key();
         let sale_manager = &mut ctx.accounts.sale_manager;
         let sale_pot = &mut ctx.accounts.sale_pot;
         let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
         let nft_bidder_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_bidder_token.data.borrow())?;
         let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
         let bidder_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.bidder_token.data.borrow())?;
-        let metadata : metaplex_token_metadata::state::Metadata =  metaplex_token_metadata::state::Metadata::from_account_info(&ctx.accounts.metadata)?;
+        let clock = Clock::get()?;
 
         if nft_manager_token.mint != *ctx.accounts.nft_mint.key {
             return Err(PoolError::InvalidTokenAccount.into());
@@ -279,104 +470,257 @@ pub mod solana_anchor {
         if bidder_token.owner != *ctx.accounts.owner.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
-        if bidder_token.amount < sale_manager.price {
-            return Err(PoolError::NotEnoughTokenAmount.into());
-        }
         if sale_manager.sale_state != 1 {
             return Err(PoolError::InvalidSaleState.into());
         }
+        if sale_manager.sale_pot != sale_pot_key {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
         if sale_pot.pool_pot != *ctx.accounts.manager_pot.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
         if sale_manager.nft_mint != *ctx.accounts.nft_mint.key {
             return Err(PoolError::InvalidMintAccount.into());
         }
+        // the sale manager signs for this account, so it has to be the listed one and not any account it is delegate of
+        if sale_manager.nft_pot != *ctx.accounts.nft_manager_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if sale_manager.is_delegated && is_delegate_listing_stale(sale_manager_key, sale_manager, &nft_manager_token) {
+            return Err(PoolError::StaleListing.into());
+        }
         if sale_manager.seller == *ctx.accounts.owner.key {
             return Err(PoolError::InvalidBidder.into());
         }
+        if sale_manager.reserved_buyer != Pubkey::default() && sale_manager.reserved_buyer != *ctx.accounts.owner.key {
+            return Err(PoolError::NotReservedBuyer.into());
+        }
+        if clock.unix_timestamp < sale_manager.starts_at {
+            return Err(PoolError::NotStarted.into());
+        }
+        if sale_manager.expires_at != 0 && sale_manager.expires_at < clock.unix_timestamp {
+            return Err(PoolError::ListingExpired.into());
+        }
+        let sale_manager_seeds = &[
+            sale_manager.pool.as_ref(),
+            sale_manager.nft_mint.as_ref(),
+            &[sale_manager.bump]
+        ];
+        let mut price = sale_manager.price;
+        let mut is_dutch = false;
+        // auction listings pass their auction data as the first remaining account, ahead of any bundle accounts.
+        // english buy-now passes the bid history and seller wallet after it
+        let mut lot_accounts = ctx.remaining_accounts;
         if sale_manager.is_auction_mode {
-            return Err(PoolError::InvalidAuctionMode.into());
+            let auction_data_info = lot_accounts.first().ok_or(PoolError::InvalidRemainingAccounts)?;
+            if sale_manager.auction_data != *auction_data_info.key {
+                return Err(PoolError::InvalidAuctionDataAccount.into());
+            }
+            let mut auction_data : ProgramAccount<AuctionData> = ProgramAccount::try_from(ctx.program_id, auction_data_info)?;
+            let mut auction_account_num = 1;
+            if auction_data.auction_type == AUCTION_TYPE_DUTCH {
+                is_dutch = true;
+                price = dutch_auction_price(
+                    auction_data.start_price,
+                    auction_data.end_price,
+                    auction_data.started_at,
+                    auction_data.ended_at,
+                    auction_data.price_drop_interval,
+                    clock.unix_timestamp,
+                )?;
+            } else if auction_data.auction_type == AUCTION_TYPE_ENGLISH && auction_data.buy_now_price > 0 {
+                if auction_data.ended_at < clock.unix_timestamp {
+                    return Err(PoolError::EndedAuction.into());
+                }
+                // a leader whose escrowed maximum reaches the buy-now price turns it off
+                if auction_data.auction_state == 2 && auction_data.leader_max_price >= auction_data.buy_now_price {
+                    return Err(PoolError::InvalidAuctionState.into());
+                }
+                // the outbid leader takes the bid back through withdraw_bid
+                price = auction_data.buy_now_price;
+                auction_data.last_bidder = *ctx.accounts.owner.key;
+                auction_data.last_bidder_token = *ctx.accounts.bidder_token.key;
+                auction_data.last_bid_receipt = Pubkey::default();
+                auction_data.locked_amount = 0;
+                if lot_accounts.len() < 3 {
+                    return Err(PoolError::InvalidRemainingAccounts.into());
+                }
+                close_bid_history(ctx.program_id, &auction_data, &lot_accounts[1], &lot_accounts[2], sale_manager.seller)?;
+                auction_account_num = 3;
+            } else {
+                return Err(PoolError::InvalidAuctionMode.into());
+            }
+            auction_data.auction_state = 3;
+            auction_data.exit(ctx.program_id)?;
+            lot_accounts = &lot_accounts[auction_account_num..];
+        }
+        // a dutch price only drops while the buy is in flight, so it may land below the price the buyer saw
+        if (is_dutch && price > _expected_price) || (!is_dutch && price != _expected_price) {
+            return Err(PoolError::PriceMismatch.into());
+        }
+        if price > _max_spend {
+            return Err(PoolError::ExceedsMaxSpend.into());
+        }
+        if bidder_token.amount < price {
+            return Err(PoolError::NotEnoughTokenAmount.into());
         }
         spl_token_transfer_without_seed(
             TokenTransferParamsWithoutSeed{
                 source : ctx.accounts.bidder_token.clone(),
                 destination : ctx.accounts.manager_pot.clone(),
-                amount : sale_manager.price,
+                amount : price,
                 authority : ctx.accounts.owner.clone(),
                 token_program : ctx.accounts.token_program.clone(),
             }
         )?;
 
-        let sale_manager_seeds = &[
-            sale_manager.pool.as_ref(),
-            sale_manager.nft_mint.as_ref(),
-            &[sale_manager.bump]
-        ];
-        invoke_signed(
-            &update_primary_sale_happened_via_token(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                sale_manager_key,
-                *ctx.accounts.nft_manager_token.key,
-            ),
-            &[
-                ctx.accounts.token_metadata_program.clone(),
-                ctx.accounts.metadata.clone(),
-                sale_manager_info3,
-                ctx.accounts.nft_manager_token.clone(),
-            ],
-            &[sale_manager_seeds]
-        )?;
-        spl_token_transfer(
-            TokenTransferParams{
-                source : ctx.accounts.nft_manager_token.clone(),
-                destination : ctx.accounts.nft_bidder_token.clone(),
-                amount : 1,
-                authority : sale_manager_info1,
-                authority_signer_seeds : sale_manager_seeds,
-                token_program : ctx.accounts.token_program.clone(),
+        let bundle = load_bundle(ctx.program_id, sale_manager.bundle, lot_accounts)?;
+        let mut lead_price = price;
+        let mut item_fees = 0;
+        if let Some(bundle) = &bundle {
+            let (bundle_lead_price, bundle_item_fees) = split_bundle_price(ctx.program_id, bundle, &lot_accounts[1..], price, pool.fee_basis_points)?;
+            lead_price = bundle_lead_price;
+            item_fees = bundle_item_fees;
+        }
+        let fee = calculate_fee(lead_price, pool.fee_basis_points)?;
+        let total_fee = fee.checked_add(item_fees).ok_or(PoolError::MathOverflow)?;
+        if total_fee > 0 {
+            if *ctx.accounts.fee_pot.key != pool.fee_pot {
+                return Err(PoolError::InvalidTokenAccount.into());
             }
-        )?;
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.manager_pot.clone(),
+                    destination : ctx.accounts.fee_pot.clone(),
+                    amount : total_fee,
+                    authority : sale_manager_info4,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
+        if sale_manager.is_delegated {
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.nft_manager_token.clone(),
+                    destination : ctx.accounts.nft_bidder_token.clone(),
+                    amount : 1,
+                    authority : sale_manager_info1,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+            // the sale manager never held the token, the buyer owns it now and signs for the primary sale
+            invoke(
+                &update_primary_sale_happened_via_token(
+                    *ctx.accounts.token_metadata_program.key,
+                    *ctx.accounts.metadata.key,
+                    *ctx.accounts.owner.key,
+                    *ctx.accounts.nft_bidder_token.key,
+                ),
+                &[
+                    ctx.accounts.token_metadata_program.clone(),
+                    ctx.accounts.metadata.clone(),
+                    ctx.accounts.owner.clone(),
+                    ctx.accounts.nft_bidder_token.clone(),
+                ]
+            )?;
+        } else {
+            invoke_signed(
+                &update_primary_sale_happened_via_token(
+                    *ctx.accounts.token_metadata_program.key,
+                    *ctx.accounts.metadata.key,
+                    sale_manager_key,
+                    *ctx.accounts.nft_manager_token.key,
+                ),
+                &[
+                    ctx.accounts.token_metadata_program.clone(),
+                    ctx.accounts.metadata.clone(),
+                    sale_manager_info3,
+                    ctx.accounts.nft_manager_token.clone(),
+                ],
+                &[sale_manager_seeds]
+            )?;
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.nft_manager_token.clone(),
+                    destination : ctx.accounts.nft_bidder_token.clone(),
+                    amount : 1,
+                    authority : sale_manager_info1,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
 
-        invoke_signed(
-            &update_metadata_accounts(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                sale_manager_key,
-                Some(*ctx.accounts.owner.key),
-                None,
-                None,
-            ),
-            &[
+        if let Some(bundle) = bundle {
+            release_bundle_items(
+                &bundle,
+                &lot_accounts[1..],
+                *ctx.accounts.owner.key,
+                true,
+                false,
+                sale_manager_info5,
+                sale_manager_seeds,
                 ctx.accounts.token_metadata_program.clone(),
-                ctx.accounts.metadata.clone(),
-                sale_manager_info2,
-            ],
-            &[sale_manager_seeds]
-        )?;
-
+                ctx.accounts.token_program.clone(),
+            )?;
+            close_bundle(ctx.program_id, bundle, lot_accounts, sale_manager.seller, true)?;
+        }
+        sale_manager.bundle = Pubkey::default();
+        sale_manager.price = price;
         sale_manager.sale_state=2;
-        sale_pot.is_used=true;        
+        sale_pot.price = lead_price;
+        sale_pot.fee = fee;
+        sale_pot.is_used=true;
+        Ok(())
+    }
+
+    pub fn update_listing_price(
+        ctx : Context<UpdateListingPrice>,
+        _price : u64,
+        ) -> ProgramResult {
+        let sale_pot_key = ctx.accounts.sale_pot.key();
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        let sale_pot = &mut ctx.accounts.sale_pot;
+        if sale_manager.sale_state != 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        if sale_manager.is_auction_mode {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
+        if sale_manager.seller != *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidSeller.into());
+        }
+        if sale_manager.sale_pot != sale_pot_key {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        sale_manager.price = _price;
+        sale_pot.price = _price;
         Ok(())
     }
 
-    pub fn redeem_nft(
-        ctx : Context<RedeemNft>
+    pub fn redeem_nft<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, RedeemNft<'info>>
         ) -> ProgramResult {
         let sale_manager_info1 = ctx.accounts.sale_manager.to_account_info().clone();
-        let sale_manager_info2 = ctx.accounts.sale_manager.to_account_info().clone();
-        let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_manager_info3 = ctx.accounts.sale_manager.to_account_info().clone();
         let sale_manager = &mut ctx.accounts.sale_manager;
         
         let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
         let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
-        let metadata : metaplex_token_metadata::state::Metadata =  metaplex_token_metadata::state::Metadata::from_account_info(&ctx.accounts.metadata)?;
         if sale_manager.sale_state != 1 {
             return Err(PoolError::InvalidSaleState.into());
         }
+        if sale_manager.is_auction_mode {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
         if sale_manager.nft_mint != *ctx.accounts.nft_mint.key {
             return Err(PoolError::InvalidMintAccount.into());
         }
+        if sale_manager.nft_pot != *ctx.accounts.nft_manager_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
         if nft_manager_token.mint != *ctx.accounts.nft_mint.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
@@ -393,40 +737,155 @@ pub mod solana_anchor {
             &[sale_manager.bump]
         ];
         
-        spl_token_transfer(
-            TokenTransferParams{
-                source : ctx.accounts.nft_manager_token.clone(),
-                destination : ctx.accounts.nft_seller_token.clone(),
-                amount : 1,
-                authority : sale_manager_info1,
-                authority_signer_seeds : sale_manager_seeds,
-                token_program : ctx.accounts.token_program.clone(),
-            }
-        )?;
-        invoke_signed(
-            &update_metadata_accounts(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                sale_manager_key,
-                Some(*ctx.accounts.owner.key),
-                None,
-                None,
-            ),
-            &[
-                ctx.accounts.token_metadata_program.clone(),
-                ctx.accounts.metadata.clone(),
-                sale_manager_info2,
-            ],
-            &[sale_manager_seeds]
-        )?;
+        if sale_manager.is_delegated {
+            spl_token_revoke(
+                TokenRevokeParams{
+                    source : ctx.accounts.nft_seller_token.clone(),
+                    owner : ctx.accounts.owner.clone(),
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        } else {
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.nft_manager_token.clone(),
+                    destination : ctx.accounts.nft_seller_token.clone(),
+                    amount : 1,
+                    authority : sale_manager_info1,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
+        if let Some(bundle) = load_bundle(ctx.program_id, sale_manager.bundle, ctx.remaining_accounts)? {
+            release_bundle_items(
+                &bundle,
+                &ctx.remaining_accounts[1..],
+                sale_manager.seller,
+                false,
+                false,
+                sale_manager_info3,
+                sale_manager_seeds,
+                ctx.accounts.token_metadata_program.clone(),
+                ctx.accounts.token_program.clone(),
+            )?;
+            close_bundle(ctx.program_id, bundle, ctx.remaining_accounts, sale_manager.seller, false)?;
+        }
+        sale_manager.bundle = Pubkey::default();
+        sale_manager.sale_state=0;
+        Ok(())
+    }
+
+    pub fn expire_listing<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, ExpireListing<'info>>
+        ) -> ProgramResult {
+        let sale_manager_info1 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info3 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        if sale_manager.sale_state != 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        if sale_manager.is_auction_mode {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
+        if sale_manager.expires_at == 0 || clock.unix_timestamp <= sale_manager.expires_at {
+            return Err(PoolError::ListingNotExpired.into());
+        }
+        if sale_manager.nft_mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if sale_manager.nft_pot != *ctx.accounts.nft_manager_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.owner != sale_manager.seller {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+
+        let sale_manager_seeds = &[
+            sale_manager.pool.as_ref(),
+            sale_manager.nft_mint.as_ref(),
+            &[sale_manager.bump]
+        ];
+        // a delegated nft never left the seller, only escrowed ones go back
+        if sale_manager.is_delegated {
+            revoke_delegation(
+                sale_manager_key,
+                sale_manager,
+                &ctx.accounts.nft_seller_token,
+                &ctx.accounts.seller,
+                &ctx.accounts.token_program,
+            )?;
+        } else {
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.nft_manager_token.clone(),
+                    destination : ctx.accounts.nft_seller_token.clone(),
+                    amount : 1,
+                    authority : sale_manager_info1,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
+        if let Some(bundle) = load_bundle(ctx.program_id, sale_manager.bundle, ctx.remaining_accounts)? {
+            release_bundle_items(
+                &bundle,
+                &ctx.remaining_accounts[1..],
+                sale_manager.seller,
+                false,
+                false,
+                sale_manager_info3,
+                sale_manager_seeds,
+                ctx.accounts.token_metadata_program.clone(),
+                ctx.accounts.token_program.clone(),
+            )?;
+            close_bundle(ctx.program_id, bundle, ctx.remaining_accounts, sale_manager.seller, false)?;
+        }
+        sale_manager.bundle = Pubkey::default();
         sale_manager.sale_state=0;
         Ok(())
     }
 
+    pub fn delist_stale(
+        ctx : Context<DelistStale>,
+        ) -> ProgramResult {
+        let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        if sale_manager.sale_state != 1 || !sale_manager.is_delegated {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        if sale_manager.nft_pot != *ctx.accounts.nft_seller_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        // a closed token account is as stale as one that was emptied or revoked
+        let is_stale = *ctx.accounts.nft_seller_token.owner != spl_token::id()
+            || state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())
+                .map_or(true, |nft_seller_token| is_delegate_listing_stale(sale_manager_key, sale_manager, &nft_seller_token));
+        if !is_stale {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        revoke_delegation(
+            sale_manager_key,
+            sale_manager,
+            &ctx.accounts.nft_seller_token,
+            &ctx.accounts.seller,
+            &ctx.accounts.token_program,
+        )?;
+        sale_manager.sale_state = 0;
+        Ok(())
+    }
+
     pub fn withdraw_fund(
         ctx : Context<WithdrawFund>,
         ) -> ProgramResult {
         let sale_manager_info = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_key = ctx.accounts.sale_manager.key();
         let sale_manager = &ctx.accounts.sale_manager;
         let sale_pot = &mut ctx.accounts.sale_pot;
         let sale_manager_seeds = &[
@@ -437,79 +896,290 @@ pub mod solana_anchor {
         if !sale_pot.is_used {
             return Err(PoolError::NotAllowedWithdrawFund.into());
         }
+        if sale_pot.sale_manager != sale_manager_key {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        if sale_pot.pool_pot != *ctx.accounts.pool_pot.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
 
         let pool_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.pool_pot.data.borrow())?;
+        let table = payout_table(
+            sale_pot.price,
+            sale_pot.fee,
+            sale_pot.is_primary,
+            sale_pot.seller_fee_basis_points,
+            &sale_pot.creators,
+        )?;
         let mut amount : u64 = 0;
-        if sale_pot.is_primary {
-            // sale_pot.seller_verified = true;
-            let mut share : u8 = 0;
-            for i in 0..sale_pot.creators.len(){
-                if sale_pot.creators[i].verified==false && sale_pot.creators[i].address==*ctx.accounts.owner.key {
-                    sale_pot.creators[i].verified=true;
-                    share = sale_pot.creators[i].share
-                }
-            }
-            amount =((sale_pot.price as f64) * (share as f64) / (100.0 as f64)) as u64;
-        } else {
-            if sale_pot.seller_verified==false && sale_pot.seller == *ctx.accounts.owner.key {
-                amount = ((sale_pot.price as f64) * ((10000-sale_pot.seller_fee_basis_points) as f64) / (10000 as f64)) as u64;
-                sale_pot.seller_verified = true;
-            }
-            let mut share : u8 = 0;
-            for i in 0..sale_pot.creators.len(){
-                if sale_pot.creators[i].verified==false && sale_pot.creators[i].address==*ctx.accounts.owner.key {
-                    sale_pot.creators[i].verified=true;
-                    share = sale_pot.creators[i].share
-                }
+        let mut is_payee = false;
+        if !sale_pot.seller_verified && sale_pot.seller == *ctx.accounts.owner.key {
+            amount = table.seller;
+            sale_pot.seller_verified = true;
+            is_payee = true;
+        }
+        for (i, c) in sale_pot.creators.iter_mut().enumerate() {
+            if !c.verified && c.address == *ctx.accounts.owner.key {
+                c.verified = true;
+                amount = amount.checked_add(table.creators[i]).ok_or(PoolError::MathOverflow)?;
+                is_payee = true;
             }
-            amount = amount + ((sale_pot.price as f64) * (sale_pot.seller_fee_basis_points as f64) / (10000 as f64) * (share as f64) / (100.0 as f64)) as u64
         }
-        if amount == 0 {
+        if !is_payee {
             return Err(PoolError::InvalidAmount.into());
         }
-
         if amount > pool_pot.amount {
-            amount = pool_pot.amount;
+            return Err(PoolError::NotEnoughTokenAmount.into());
         }
-        
-        spl_token_transfer(
-            TokenTransferParams{
-                source : ctx.accounts.pool_pot.clone(),
-                destination : ctx.accounts.withdraw_pot.clone(),
-                amount : amount,
-                authority : sale_manager_info,
-                authority_signer_seeds : sale_manager_seeds,
-                token_program : ctx.accounts.token_program.clone(),
-            }
+
+        // a share that rounds down to zero is marked paid without a transfer
+        if amount > 0 {
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.pool_pot.clone(),
+                    destination : ctx.accounts.withdraw_pot.clone(),
+                    amount,
+                    authority : sale_manager_info,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn settle_sale<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, SettleSale<'info>>,
+        ) -> ProgramResult {
+        let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_manager = &ctx.accounts.sale_manager;
+        let sale_pot = &mut ctx.accounts.sale_pot;
+        let sale_manager_seeds = &[
+            sale_manager.pool.as_ref(),
+            sale_manager.nft_mint.as_ref(),
+            &[sale_manager.bump]
+        ];
+        if !sale_pot.is_used {
+            return Err(PoolError::NotAllowedWithdrawFund.into());
+        }
+        if sale_pot.sale_manager != sale_manager_key {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        if sale_pot.pool_pot != *ctx.accounts.pool_pot.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        // remaining accounts : seller payout token, then one payout token per creator in sale_pot order
+        if ctx.remaining_accounts.len() != 1 + sale_pot.creators.len() {
+            return Err(PoolError::InvalidRemainingAccounts.into());
+        }
+
+        let pool_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.pool_pot.data.borrow())?;
+        let table = payout_table(
+            sale_pot.price,
+            sale_pot.fee,
+            sale_pot.is_primary,
+            sale_pot.seller_fee_basis_points,
+            &sale_pot.creators,
         )?;
 
+        let mut payouts : Vec<(usize, u64)> = Vec::new();
+        if !sale_pot.seller_verified {
+            payouts.push((0, table.seller));
+            sale_pot.seller_verified = true;
+        }
+        for (i, c) in sale_pot.creators.iter_mut().enumerate() {
+            if !c.verified {
+                payouts.push((i + 1, table.creators[i]));
+                c.verified = true;
+            }
+        }
+
+        for (index, amount) in payouts {
+            if amount == 0 {
+                continue;
+            }
+            let recipient = if index == 0 { sale_pot.seller } else { sale_pot.creators[index - 1].address };
+            let payout_token_info = &ctx.remaining_accounts[index];
+            if *payout_token_info.owner != spl_token::id() {
+                return Err(PoolError::InvalidTokenAccount.into());
+            }
+            let payout_token : state::Account = state::Account::unpack_from_slice(&payout_token_info.data.borrow())?;
+            if payout_token.owner != recipient {
+                return Err(PoolError::InvalidTokenAccount.into());
+            }
+            if payout_token.mint != pool_pot.mint {
+                return Err(PoolError::InvalidMintAccount.into());
+            }
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.pool_pot.clone(),
+                    destination : payout_token_info.clone(),
+                    amount,
+                    authority : ctx.accounts.sale_manager.to_account_info().clone(),
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn sell_nft_by_auction(
+        ctx : Context<SellNftByEnglishAuction>,
+        _price : u64,
+        _starts_at : i64,
+        _ended_at : i64,
+        _extension_window : i64,
+        _extension : i64,
+        _reserve_price : u64,
+        _buy_now_price : Option<u64>,
+        _gap_tick_percentage : u8,
+        _min_increment : u64,
+        ) -> ProgramResult {
+        let pool = &ctx.accounts.listing.pool;
+        if _extension_window < 0 || _extension_window > pool.max_extension_window {
+            return Err(PoolError::InvalidExtension.into());
+        }
+        if _extension < 0 || _extension > pool.max_extension {
+            return Err(PoolError::InvalidExtension.into());
+        }
+        // the seller bids up either by percentage or by absolute amount, not both
+        if (_gap_tick_percentage == 0) == (_min_increment == 0) {
+            return Err(PoolError::InvalidBidIncrement.into());
+        }
+        if _gap_tick_percentage > pool.max_gap_tick_percentage || _min_increment > pool.max_min_increment {
+            return Err(PoolError::InvalidBidIncrement.into());
+        }
+        let buy_now_price = _buy_now_price.unwrap_or(0);
+        if _buy_now_price.is_some() && (buy_now_price <= _price || buy_now_price < _reserve_price) {
+            return Err(PoolError::InvalidPrice.into());
+        }
+        let starts_at = open_auction_listing(&mut ctx.accounts.listing, _price, _starts_at)?;
+
+        // only english auctions record bids
+        let auction_data = &mut ctx.accounts.listing.auction_data;
+        let bid_history = &mut ctx.accounts.bid_history;
+        auction_data.bid_history = bid_history.key();
+        bid_history.auction_data = auction_data.key();
+        bid_history.total = 0;
+        auction_data.auction_type = AUCTION_TYPE_ENGLISH;
+        auction_data.started_at = starts_at;
+        auction_data.ended_at=_ended_at.checked_add(starts_at).ok_or(PoolError::MathOverflow)?;
+        auction_data.auction_state = 1;
+        auction_data.gap_tick_percentage = _gap_tick_percentage;
+        auction_data.min_increment = _min_increment;
+        auction_data.extension_window = _extension_window;
+        auction_data.extension = _extension;
+        auction_data.reserve_price = _reserve_price;
+        auction_data.buy_now_price = buy_now_price;
+        Ok(())
+    }
+    
+    pub fn sell_nft_by_sealed_auction(
         ctx : Context<SellNftByAuction>,
         _price : u64,
+        _starts_at : i64,
+        _ended_at : i64,
+        _reveal_duration : i64,
+        _reserve_price : u64,
+        _is_second_price : bool,
+        ) -> ProgramResult {
+        if _ended_at <= 0 || _reveal_duration <= 0 {
+            return Err(PoolError::InvalidAuctionSchedule.into());
+        }
+        let starts_at = open_auction_listing(ctx.accounts, _price, _starts_at)?;
+
+        let auction_data = &mut ctx.accounts.auction_data;
+        auction_data.auction_type = if _is_second_price { AUCTION_TYPE_SEALED_SECOND_PRICE } else { AUCTION_TYPE_SEALED_FIRST_PRICE };
+        auction_data.started_at = starts_at;
+        // bids are committed until ended_at and revealed until reveal_ended_at
+        auction_data.ended_at = _ended_at.checked_add(starts_at).ok_or(PoolError::MathOverflow)?;
+        auction_data.reveal_ended_at = auction_data.ended_at.checked_add(_reveal_duration).ok_or(PoolError::MathOverflow)?;
+        auction_data.auction_state = 1;
+        auction_data.start_price = _price;
+        auction_data.reserve_price = _reserve_price;
+        Ok(())
+    }
+
+    pub fn sell_nft_by_dutch_auction(
+        ctx : Context<SellNftByAuction>,
+        _start_price : u64,
+        _end_price : u64,
+        _starts_at : i64,
         _ended_at : i64,
+        _price_drop_interval : i64,
+        ) -> ProgramResult {
+        if _start_price <= _end_price || _price_drop_interval <= 0 || _ended_at < _price_drop_interval {
+            return Err(PoolError::InvalidAuctionSchedule.into());
+        }
+        let starts_at = open_auction_listing(ctx.accounts, _start_price, _starts_at)?;
+
+        let auction_data = &mut ctx.accounts.auction_data;
+        auction_data.auction_type = AUCTION_TYPE_DUTCH;
+        auction_data.started_at = starts_at;
+        auction_data.ended_at = _ended_at.checked_add(starts_at).ok_or(PoolError::MathOverflow)?;
+        auction_data.auction_state = 1;
+        auction_data.start_price = _start_price;
+        auction_data.end_price = _end_price;
+        auction_data.price_drop_interval = _price_drop_interval;
+        Ok(())
+    }
+
+    pub fn init_bundle(
+        ctx : Context<InitBundle>,
+        ) -> ProgramResult {
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        let bundle = &mut ctx.accounts.bundle;
+        if sale_manager.seller != *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidSeller.into());
+        }
+        if sale_manager.bundle != Pubkey::default() || sale_manager.is_delegated {
+            return Err(PoolError::InvalidBundle.into());
+        }
+        check_lot_open(ctx.program_id, sale_manager, &ctx.accounts.auction_data)?;
+        sale_manager.bundle = bundle.key();
+        bundle.sale_manager = sale_manager.key();
+        Ok(())
+    }
+
+    pub fn add_bundle_item(
+        ctx : Context<AddBundleItem>,
         ) -> ProgramResult {
         let pool = &ctx.accounts.pool;
         let sale_manager_key = ctx.accounts.sale_manager.key();
-        let sale_manager = &mut ctx.accounts.sale_manager;
-        sale_manager.sale_pot = ctx.accounts.sale_pot.key();
-        sale_manager.auction_data = ctx.accounts.auction_data.key();
+        let sale_manager = &ctx.accounts.sale_manager;
+        let bundle = &mut ctx.accounts.bundle;
         let sale_pot = &mut ctx.accounts.sale_pot;
-        let auction_data = &mut ctx.accounts.auction_data;
         let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
         let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
-        let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
-        let metadata : metaplex_token_metadata::state::Metadata =  metaplex_token_metadata::state::Metadata::from_account_info(&ctx.accounts.metadata)?;
-        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        let nft_bundle_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_bundle_token.data.borrow())?;
+        let metadata = load_metadata(&ctx.accounts.metadata, ctx.accounts.nft_mint.key)?;
+        if sale_manager.seller != *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidSeller.into());
+        }
+        if sale_manager.bundle != bundle.key() || bundle.sale_manager != sale_manager_key {
+            return Err(PoolError::InvalidBundle.into());
+        }
+        check_lot_open(ctx.program_id, sale_manager, &ctx.accounts.auction_data)?;
+        if bundle.items.len() >= MAX_BUNDLE_ITEM_NUM {
+            return Err(PoolError::InvalidBundle.into());
+        }
+        let nft_mint_key = *ctx.accounts.nft_mint.key;
+        if sale_manager.nft_mint == nft_mint_key || bundle.items.iter().any(|item| item.nft_mint == nft_mint_key) {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
         if nft_seller_token.owner != *ctx.accounts.owner.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
         if nft_seller_token.mint != *ctx.accounts.nft_mint.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
-        if nft_manager_token.mint != *ctx.accounts.nft_mint.key {
+        if nft_bundle_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_bundle_token.owner != sale_manager_key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
         if manager_pot.mint != pool.sale_mint {
@@ -518,74 +1188,53 @@ pub mod solana_anchor {
         if manager_pot.owner != sale_manager_key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
+        if sale_manager.sale_pot != ctx.accounts.lead_sale_pot.key() {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        if ctx.accounts.lead_sale_pot.pool_pot != *ctx.accounts.manager_pot.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if metadata.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMetadata.into());
+        }
 
-        sale_manager.seller=*ctx.accounts.owner.key;
-        sale_manager.price=_price;
-        sale_manager.sale_state =1;
-        sale_manager.nft_pot = *ctx.accounts.nft_manager_token.key;
-        sale_manager.is_auction_mode = true;
-
-        auction_data.ended_at=_ended_at+clock.unix_timestamp;
-        auction_data.auction_state = 1;
-        auction_data.gap_tick_percentage =10;
-
-        // sale_manager.is_primary = metadata.primary_sale_happened;
+        // every item keeps its own royalty snapshot and is paid out of the lead listing's pot
         sale_pot.sale_manager = sale_manager_key;
         sale_pot.is_used = false;
-        sale_pot.price = _price;
-        sale_pot.pool_pot=*ctx.accounts.manager_pot.key;
-        sale_pot.is_primary = !metadata.primary_sale_happened;
-        if sale_pot.is_primary {
-            sale_pot.seller_verified = true;
-        } else {
-            sale_pot.seller_verified = false;
-        }
+        sale_pot.price = 0;
+        sale_pot.pool_pot = *ctx.accounts.manager_pot.key;
         sale_pot.seller = *ctx.accounts.owner.key;
-        if let Some(creators) = metadata.data.creators{
-            for c in creators {
-                sale_pot.creators.push(Creator{
-                    address : c.address,
-                    verified : false,
-                    share : c.share,
-                })
-            }
-        }
+        set_royalty(sale_pot, &metadata)?;
+        bundle.items.push(BundleItem{
+            nft_mint : *ctx.accounts.nft_mint.key,
+            nft_pot : *ctx.accounts.nft_bundle_token.key,
+            sale_pot : sale_pot.key(),
+        });
         spl_token_transfer_without_seed(
             TokenTransferParamsWithoutSeed{
                 source : ctx.accounts.nft_seller_token.clone(),
-                destination : ctx.accounts.nft_manager_token.clone(),
+                destination : ctx.accounts.nft_bundle_token.clone(),
                 amount : 1,
                 authority : ctx.accounts.owner.clone(),
                 token_program : ctx.accounts.token_program.clone(),
             }
         )?;
-        invoke(
-            &update_metadata_accounts(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                *ctx.accounts.owner.key,
-                Some(sale_manager_key),
-                None,
-                None,
-            ),
-            &[
-                ctx.accounts.token_metadata_program.clone(),
-                ctx.accounts.metadata.clone(),
-                ctx.accounts.owner.clone(),
-            ]
-        )?;
-        Ok(())        
+        Ok(())
     }
-    
+
     pub fn place_bid(
         ctx : Context<PlaceBid>,
         _price : u64,
+        _max_price : u64,
+        _expected_price : u64,
+        _max_spend : u64,
         ) -> ProgramResult {
-        let sale_manager_info = ctx.accounts.sale_manager.to_account_info().clone();
         let pool = &ctx.accounts.pool;
         let sale_manager = &mut ctx.accounts.sale_manager;
         let sale_pot = &mut ctx.accounts.sale_pot;
         let auction_data = &mut ctx.accounts.auction_data;
+        let bid_receipt = &mut ctx.accounts.bid_receipt;
+        let bid_history = &mut ctx.accounts.bid_history;
 
         let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
         let bidder_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.bidder_token.data.borrow())?;
@@ -597,6 +1246,12 @@ pub mod solana_anchor {
         if !sale_manager.is_auction_mode {
             return Err(PoolError::InvalidAuctionMode.into());
         }
+        if sale_manager.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if auction_data.auction_type != AUCTION_TYPE_ENGLISH {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
         if manager_pot.mint != pool.sale_mint {
             return Err(PoolError::InvalidTokenAccount.into());
         }
@@ -606,14 +1261,23 @@ pub mod solana_anchor {
         if sale_manager.sale_state != 1 {
             return Err(PoolError::InvalidSaleState.into());
         }
-        if sale_pot.pool_pot != *ctx.accounts.manager_pot.key {
+        if sale_pot.pool_pot != *ctx.accounts.manager_pot.key || auction_data.pool_pot != *ctx.accounts.manager_pot.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
         if sale_manager.seller == *ctx.accounts.owner.key {
             return Err(PoolError::InvalidBidder.into());
         }
-        if bidder_token.amount < _price {
-            return Err(PoolError::NotEnoughTokenAmount.into());
+        if clock.unix_timestamp < sale_manager.starts_at {
+            return Err(PoolError::NotStarted.into());
+        }
+        if sale_manager.price != _expected_price {
+            return Err(PoolError::PriceMismatch.into());
+        }
+        if bid_receipt.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidBidReceipt.into());
+        }
+        if auction_data.bid_history != bid_history.key() {
+            return Err(PoolError::InvalidBidHistory.into());
         }
         if _price < sale_manager.price {
             return Err(PoolError::NotEnoughTokenAmount.into());
@@ -627,96 +1291,442 @@ pub mod solana_anchor {
             return Err(PoolError::EndedAuction.into());
         }
         
-        if auction_data.auction_state == 2 {
-            if _price < sale_manager.price * (100u64 + auction_data.gap_tick_percentage as u64) / 100u64 {
+        let max_price = _max_price.max(_price);
+        let is_leader = auction_data.auction_state == 2 && auction_data.last_bid_receipt == bid_receipt.key();
+        // every bid escrows its maximum before it is resolved, so a losing bid costs the same as a leading one.
+        // the receipt may still hold funds from earlier bids, only the difference to the maximum is deposited.
+        // an outbid challenger takes the deposit back through withdraw_bid
+        let deposit = max_price.saturating_sub(bid_receipt.amount);
+        if deposit > _max_spend {
+            return Err(PoolError::ExceedsMaxSpend.into());
+        }
+        if bidder_token.amount < deposit {
+            return Err(PoolError::NotEnoughTokenAmount.into());
+        }
+        if deposit > 0 {
+            spl_token_transfer_without_seed(
+                TokenTransferParamsWithoutSeed{
+                    source : ctx.accounts.bidder_token.clone(),
+                    destination : ctx.accounts.manager_pot.clone(),
+                    amount : deposit,
+                    authority : ctx.accounts.owner.clone(),
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+            bid_receipt.amount = bid_receipt.amount.checked_add(deposit).ok_or(PoolError::MathOverflow)?;
+        }
+        let mut price = _price;
+        if is_leader {
+            // the leader only raises its maximum, the current price stays
+            if max_price <= auction_data.leader_max_price {
+                return Err(PoolError::InvalidPrice.into());
+            }
+            price = sale_manager.price;
+        } else if auction_data.auction_state == 2 {
+            if _price < min_next_bid(sale_manager.price, auction_data.gap_tick_percentage, auction_data.min_increment)? {
                 return Err(PoolError::NotEnoughTokenAmountForGapTick.into());
             }
-            let sale_manager_seeds = &[
-                sale_manager.pool.as_ref(),
+            let outcome = resolve_proxy_bid(
+                auction_data.leader_max_price,
+                _price,
+                max_price,
+                auction_data.gap_tick_percentage,
+                auction_data.min_increment,
+            );
+            if !outcome.challenger_leads {
+                // the leader's maximum covers this bid, so it is outbid on the spot
+                sale_manager.price = raise_to_reserve(outcome.price, auction_data.leader_max_price, auction_data.reserve_price);
+                sale_pot.price = sale_manager.price;
+                record_bid(bid_history, *ctx.accounts.owner.key, _price, clock.unix_timestamp)?;
+                record_bid(bid_history, auction_data.last_bidder, sale_manager.price, clock.unix_timestamp)?;
+                if auction_data.ended_at - clock.unix_timestamp <= auction_data.extension_window {
+                    auction_data.ended_at = auction_data.ended_at.checked_add(auction_data.extension).ok_or(PoolError::MathOverflow)?;
+                }
+                return Ok(());
+            }
+            price = outcome.price;
+        }
+        let price = raise_to_reserve(price, max_price, auction_data.reserve_price);
+
+        if !is_leader || price != sale_manager.price {
+            record_bid(bid_history, *ctx.accounts.owner.key, price, clock.unix_timestamp)?;
+        }
+        sale_manager.price = price;
+        sale_pot.price = price;
+
+        auction_data.last_bidder = *ctx.accounts.owner.key;
+        auction_data.last_bidder_token = *ctx.accounts.bidder_token.key;
+        auction_data.last_bid_receipt = bid_receipt.key();
+        auction_data.leader_max_price = max_price;
+        auction_data.locked_amount = max_price;
+        auction_data.auction_state = 2;
+        // bids in the last extension_window seconds push the end out to stop sniping
+        if auction_data.ended_at - clock.unix_timestamp <= auction_data.extension_window {
+            auction_data.ended_at = auction_data.ended_at.checked_add(auction_data.extension).ok_or(PoolError::MathOverflow)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn init_bid_receipt(
+        ctx : Context<InitBidReceipt>,
+        _bump : u8,
+        ) -> ProgramResult {
+        let sale_manager = &ctx.accounts.sale_manager;
+        let auction_data = &ctx.accounts.auction_data;
+        let bid_receipt = &mut ctx.accounts.bid_receipt;
+        if sale_manager.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if auction_data.auction_type != AUCTION_TYPE_ENGLISH {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
+        bid_receipt.auction_data = auction_data.key();
+        bid_receipt.sale_manager = sale_manager.key();
+        bid_receipt.bidder = *ctx.accounts.owner.key;
+        bid_receipt.amount = 0;
+        bid_receipt.bump = _bump;
+        Ok(())
+    }
+
+    pub fn withdraw_bid(
+        ctx : Context<WithdrawBid>,
+        ) -> ProgramResult {
+        let sale_manager_info = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_manager = &ctx.accounts.sale_manager;
+        let auction_data = &ctx.accounts.auction_data;
+        let bid_receipt = &mut ctx.accounts.bid_receipt;
+        let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
+        let bidder_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.bidder_token.data.borrow())?;
+        if bid_receipt.sale_manager != sale_manager_key || bid_receipt.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidBidReceipt.into());
+        }
+        if manager_pot.owner != sale_manager_key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if *ctx.accounts.manager_pot.key != auction_data.pool_pot {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if bidder_token.mint != manager_pot.mint {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+
+        // the leading bid stays in escrow unless the auction failed
+        let mut locked = 0;
+        if auction_data.last_bid_receipt == bid_receipt.key() && auction_data.auction_state != 4 {
+            locked = auction_data.locked_amount;
+        }
+        let amount = bid_receipt.amount.saturating_sub(locked);
+        // nothing is left for the bidder once outbid, or once the auction sold or failed
+        let is_done = auction_data.last_bid_receipt != bid_receipt.key() || auction_data.auction_state == 3 || auction_data.auction_state == 4;
+        if amount == 0 && !is_done {
+            return Err(PoolError::InvalidAmount.into());
+        }
+
+        if amount > 0 {
+            let sale_manager_seeds = &[
+                sale_manager.pool.as_ref(),
                 sale_manager.nft_mint.as_ref(),
                 &[sale_manager.bump]
             ];
-            if *ctx.accounts.prev_bidder_token.key != auction_data.last_bidder_token {
-                return Err(PoolError::InvalidPrevBidderToken.into());
-            }
             spl_token_transfer(
                 TokenTransferParams{
                     source : ctx.accounts.manager_pot.clone(),
-                    destination : ctx.accounts.prev_bidder_token.clone(),
-                    amount : sale_manager.price,
+                    destination : ctx.accounts.bidder_token.clone(),
+                    amount,
                     authority : sale_manager_info,
                     authority_signer_seeds : sale_manager_seeds,
                     token_program : ctx.accounts.token_program.clone(),
                 }
             )?;
+            bid_receipt.amount -= amount;
+        }
+        if is_done {
+            bid_receipt.close(ctx.accounts.owner.clone())?;
+        }
+        Ok(())
+    }
+
+    pub fn commit_bid(
+        ctx : Context<CommitBid>,
+        _commitment : [u8; 32],
+        _collateral : u64,
+        _bump : u8,
+        ) -> ProgramResult {
+        let pool = &ctx.accounts.pool;
+        let sale_manager = &ctx.accounts.sale_manager;
+        let auction_data = &mut ctx.accounts.auction_data;
+        let sealed_bid = &mut ctx.accounts.sealed_bid;
+        let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
+        let bidder_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.bidder_token.data.borrow())?;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        if sale_manager.pool != pool.key() {
+            return Err(PoolError::InvalidPoolAccount.into());
+        }
+        if sale_manager.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if auction_data.auction_type != AUCTION_TYPE_SEALED_FIRST_PRICE && auction_data.auction_type != AUCTION_TYPE_SEALED_SECOND_PRICE {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
+        if sale_manager.sale_state != 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        if clock.unix_timestamp < sale_manager.starts_at {
+            return Err(PoolError::NotStarted.into());
+        }
+        if auction_data.ended_at < clock.unix_timestamp {
+            return Err(PoolError::EndedAuction.into());
+        }
+        if manager_pot.mint != pool.sale_mint || manager_pot.owner != sale_manager.key() {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if *ctx.accounts.manager_pot.key != auction_data.pool_pot {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if bidder_token.mint != pool.sale_mint {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if sale_manager.seller == *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidBidder.into());
+        }
+        if _collateral < auction_data.start_price {
+            return Err(PoolError::NotEnoughTokenAmount.into());
+        }
+        if bidder_token.amount < _collateral {
+            return Err(PoolError::NotEnoughTokenAmount.into());
         }
 
         spl_token_transfer_without_seed(
             TokenTransferParamsWithoutSeed{
                 source : ctx.accounts.bidder_token.clone(),
                 destination : ctx.accounts.manager_pot.clone(),
-                amount : _price,
+                amount : _collateral,
                 authority : ctx.accounts.owner.clone(),
                 token_program : ctx.accounts.token_program.clone(),
             }
         )?;
 
-        sale_manager.price = _price;
-        sale_manager.sale_state = 2;
-        sale_pot.price = _price;
+        sealed_bid.auction_data = auction_data.key();
+        sealed_bid.bidder = *ctx.accounts.owner.key;
+        sealed_bid.bidder_token = *ctx.accounts.bidder_token.key;
+        sealed_bid.commitment = _commitment;
+        sealed_bid.collateral = _collateral;
+        sealed_bid.price = 0;
+        sealed_bid.is_revealed = false;
+        sealed_bid.bump = _bump;
+        auction_data.commit_count += 1;
+        Ok(())
+    }
 
-        auction_data.last_bidder = *ctx.accounts.owner.key;
-        auction_data.last_bidder_token = *ctx.accounts.bidder_token.key;
-        auction_data.auction_state = 2;
+    pub fn reveal_bid(
+        ctx : Context<RevealBid>,
+        _price : u64,
+        _salt : [u8; 32],
+        ) -> ProgramResult {
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        let auction_data = &mut ctx.accounts.auction_data;
+        let sealed_bid = &mut ctx.accounts.sealed_bid;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        if sale_manager.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if sale_manager.sale_state != 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        if clock.unix_timestamp <= auction_data.ended_at || auction_data.reveal_ended_at < clock.unix_timestamp {
+            return Err(PoolError::InvalidAuctionState.into());
+        }
+        if sealed_bid.is_revealed {
+            return Err(PoolError::AlreadyRevealed.into());
+        }
+        // commitment = keccak(price as little endian u64, salt, bidder)
+        let hash = hashv(&[&_price.to_le_bytes(), &_salt, ctx.accounts.owner.key.as_ref()]);
+        if hash.to_bytes() != sealed_bid.commitment {
+            return Err(PoolError::InvalidCommitment.into());
+        }
+        if _price > sealed_bid.collateral {
+            return Err(PoolError::NotEnoughTokenAmount.into());
+        }
+        sealed_bid.is_revealed = true;
+        sealed_bid.price = _price;
+        if _price < auction_data.start_price {
+            return Ok(());
+        }
+
+        // earlier reveals win ties
+        if auction_data.auction_state != 2 || _price > sale_manager.price {
+            if auction_data.auction_state == 2 {
+                auction_data.second_price = sale_manager.price;
+            }
+            sale_manager.price = _price;
+            auction_data.last_bidder = *ctx.accounts.owner.key;
+            auction_data.last_bidder_token = sealed_bid.bidder_token;
+            auction_data.auction_state = 2;
+        } else if _price > auction_data.second_price {
+            auction_data.second_price = _price;
+        }
+        Ok(())
+    }
+
+    pub fn withdraw_sealed_bid(
+        ctx : Context<WithdrawSealedBid>,
+        ) -> ProgramResult {
+        let sale_manager_info = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager = &ctx.accounts.sale_manager;
+        let auction_data = &ctx.accounts.auction_data;
+        let sealed_bid = &ctx.accounts.sealed_bid;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        // checked against the auction itself, the sale manager may already be relisted
+        if sealed_bid.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if *ctx.accounts.manager_pot.key != auction_data.pool_pot {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if *ctx.accounts.bidder_token.key != sealed_bid.bidder_token {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if clock.unix_timestamp <= auction_data.reveal_ended_at {
+            return Err(PoolError::InvalidAuctionState.into());
+        }
 
+        // the winner leaves the clearing price in escrow unless the auction failed
+        let mut amount = sealed_bid.collateral;
+        let is_winner = auction_data.last_bidder == sealed_bid.bidder
+            && (auction_data.auction_state == 2 || auction_data.auction_state == 3)
+            && sealed_bid.price >= auction_data.reserve_price;
+        if is_winner {
+            let price = sealed_auction_price(
+                auction_data.auction_type == AUCTION_TYPE_SEALED_SECOND_PRICE,
+                sealed_bid.price,
+                auction_data.second_price,
+                auction_data.start_price.max(auction_data.reserve_price),
+            );
+            amount = amount.checked_sub(price).ok_or(PoolError::MathOverflow)?;
+        }
+
+        if amount > 0 {
+            let sale_manager_seeds = &[
+                sale_manager.pool.as_ref(),
+                sale_manager.nft_mint.as_ref(),
+                &[sale_manager.bump]
+            ];
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.manager_pot.clone(),
+                    destination : ctx.accounts.bidder_token.clone(),
+                    amount,
+                    authority : sale_manager_info,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
         Ok(())
     }
 
-    pub fn claim_bid(
-        ctx : Context<ClaimBid>,
+    pub fn claim_bid<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, ClaimBid<'info>>,
         ) -> ProgramResult {
         let sale_manager_info1 = ctx.accounts.sale_manager.to_account_info().clone();
-        let sale_manager_info2 = ctx.accounts.sale_manager.to_account_info().clone();
         let sale_manager_info3 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info4 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info5 = ctx.accounts.sale_manager.to_account_info().clone();
         let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_pot_key = ctx.accounts.sale_pot.key();
+        let pool = &ctx.accounts.pool;
         let sale_manager = &mut ctx.accounts.sale_manager;
         let sale_pot = &mut ctx.accounts.sale_pot;
         let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
         if sale_manager.auction_data != ctx.accounts.auction_data.key() {
             return Err(PoolError::InvalidAuctionDataAccount.into());
         }
+        if sale_manager.pool != pool.key() {
+            return Err(PoolError::InvalidPoolAccount.into());
+        }
+        if sale_manager.sale_pot != sale_pot_key {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        if sale_pot.pool_pot != *ctx.accounts.manager_pot.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
         let auction_data = &mut ctx.accounts.auction_data;
         let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
         let nft_bidder_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_bidder_token.data.borrow())?;
+        if sale_manager.nft_pot != *ctx.accounts.nft_manager_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
         if nft_manager_token.mint != *ctx.accounts.nft_mint.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
         if nft_bidder_token.mint != *ctx.accounts.nft_mint.key {
             return Err(PoolError::InvalidTokenAccount.into());
         }
-        if sale_manager.sale_state == 2 {
+        if sale_manager.sale_state != 1 {
             return Err(PoolError::InvalidSaleState.into());
         }
         if !sale_manager.is_auction_mode {
             return Err(PoolError::InvalidAuctionMode.into());
         }
+        let is_sealed = auction_data.auction_type == AUCTION_TYPE_SEALED_FIRST_PRICE || auction_data.auction_type == AUCTION_TYPE_SEALED_SECOND_PRICE;
+        if auction_data.auction_type != AUCTION_TYPE_ENGLISH && !is_sealed {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
         if auction_data.last_bidder != *ctx.accounts.owner.key {
             return Err(PoolError::InvalidBidder.into());
         }
-        if auction_data.ended_at < clock.unix_timestamp {
+        let closes_at = if is_sealed { auction_data.reveal_ended_at } else { auction_data.ended_at };
+        if closes_at < clock.unix_timestamp {
             auction_data.auction_state = 3;
             sale_pot.is_used = true;
         }
         if auction_data.auction_state != 3 {
             return Err(PoolError::InvalidAuctionState.into());
         }
-
+        if sale_manager.price < auction_data.reserve_price {
+            return Err(PoolError::ReserveNotMet.into());
+        }
+        if is_sealed {
+            sale_manager.price = sealed_auction_price(
+                auction_data.auction_type == AUCTION_TYPE_SEALED_SECOND_PRICE,
+                sale_manager.price,
+                auction_data.second_price,
+                auction_data.start_price.max(auction_data.reserve_price),
+            );
+            sale_pot.price = sale_manager.price;
+        }
 
         let sale_manager_seeds = &[
             sale_manager.pool.as_ref(),
             sale_manager.nft_mint.as_ref(),
             &[sale_manager.bump]
         ];
+        let bundle = load_bundle(ctx.program_id, sale_manager.bundle, ctx.remaining_accounts)?;
+        let mut item_fees = 0;
+        if let Some(bundle) = &bundle {
+            let (bundle_lead_price, bundle_item_fees) = split_bundle_price(ctx.program_id, bundle, &ctx.remaining_accounts[1..], sale_manager.price, pool.fee_basis_points)?;
+            sale_pot.price = bundle_lead_price;
+            item_fees = bundle_item_fees;
+        }
+        let fee = calculate_fee(sale_pot.price, pool.fee_basis_points)?;
+        let total_fee = fee.checked_add(item_fees).ok_or(PoolError::MathOverflow)?;
+        if total_fee > 0 {
+            if *ctx.accounts.fee_pot.key != pool.fee_pot {
+                return Err(PoolError::InvalidTokenAccount.into());
+            }
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.manager_pot.clone(),
+                    destination : ctx.accounts.fee_pot.clone(),
+                    amount : total_fee,
+                    authority : sale_manager_info4,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
         invoke_signed(
             &update_primary_sale_happened_via_token(
                 *ctx.accounts.token_metadata_program.key,
@@ -743,54 +1753,1439 @@ pub mod solana_anchor {
             }
         )?;
 
-        invoke_signed(
-            &update_metadata_accounts(
-                *ctx.accounts.token_metadata_program.key,
-                *ctx.accounts.metadata.key,
-                sale_manager_key,
-                Some(*ctx.accounts.owner.key),
-                None,
-                None,
-            ),
-            &[
+        if let Some(bundle) = bundle {
+            release_bundle_items(
+                &bundle,
+                &ctx.remaining_accounts[1..],
+                *ctx.accounts.owner.key,
+                true,
+                false,
+                sale_manager_info5,
+                sale_manager_seeds,
                 ctx.accounts.token_metadata_program.clone(),
-                ctx.accounts.metadata.clone(),
-                sale_manager_info2,
-            ],
-            &[sale_manager_seeds]
-        )?;
+                ctx.accounts.token_program.clone(),
+            )?;
+            close_bundle(ctx.program_id, bundle, ctx.remaining_accounts, sale_manager.seller, true)?;
+        }
+        sale_manager.bundle = Pubkey::default();
+        sale_manager.sale_state = 2;
+        sale_pot.fee = fee;
+        // the winner can withdraw what its maximum bid held above the final price
+        auction_data.locked_amount = sale_manager.price;
+        close_bid_history(ctx.program_id, auction_data, &ctx.accounts.bid_history, &ctx.accounts.seller, sale_manager.seller)?;
         Ok(())
     }
 
-    // pub fn check_auction_ended(
-    //     ctx : Context<CheckAuctionEnded>,
-    //     ) -> ProgramResult {
-    //     let auction_data = &mut ctx.accounts.auction_data;
-    //     let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
-    //     if auction_data.ended_at < clock.unix_timestamp {
-    //         auction_data.auction_state = 3;
-    //         sale_pot.is_used = true;
-    //     }
-    //     Ok(())
-    // }
-}
-
-// #[derive(Accounts)]
-// pub struct CheckAuctionEnded<'info> {
-//     #[account(mut,signer)]
-//     owner : AccountInfo<'info>,
+    pub fn cancel_auction<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, CancelAuction<'info>>,
+        ) -> ProgramResult {
+        let sale_manager_info1 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info3 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_pot_key = ctx.accounts.sale_pot.key();
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        let auction_data = &ctx.accounts.auction_data;
+        let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
+        let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
+        if sale_manager.seller != *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidSeller.into());
+        }
+        if sale_manager.sale_state != 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        if !sale_manager.is_auction_mode {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
+        if sale_manager.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if sale_manager.sale_pot != sale_pot_key {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        if sale_manager.nft_mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if sale_manager.nft_pot != *ctx.accounts.nft_manager_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_manager_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        // only auctions nobody has bid on yet, sealed bidders need auction_data to get their collateral back
+        if auction_data.auction_state != 1 || auction_data.commit_count != 0 {
+            return Err(PoolError::InvalidAuctionState.into());
+        }
 
-//     #[account(mut)]
-//     auction_data : ProgramAccount<'info,AuctionData>,
+        close_bid_history(ctx.program_id, auction_data, &ctx.accounts.bid_history, &ctx.accounts.owner, sale_manager.seller)?;
 
-//     clock_sysvar : AccountInfo<'info>,    
-// }
+        let sale_manager_seeds = &[
+            sale_manager.pool.as_ref(),
+            sale_manager.nft_mint.as_ref(),
+            &[sale_manager.bump]
+        ];
+        spl_token_transfer(
+            TokenTransferParams{
+                source : ctx.accounts.nft_manager_token.clone(),
+                destination : ctx.accounts.nft_seller_token.clone(),
+                amount : 1,
+                authority : sale_manager_info1,
+                authority_signer_seeds : sale_manager_seeds,
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+
+        if let Some(bundle) = load_bundle(ctx.program_id, sale_manager.bundle, ctx.remaining_accounts)? {
+            release_bundle_items(
+                &bundle,
+                &ctx.remaining_accounts[1..],
+                sale_manager.seller,
+                false,
+                false,
+                sale_manager_info3,
+                sale_manager_seeds,
+                ctx.accounts.token_metadata_program.clone(),
+                ctx.accounts.token_program.clone(),
+            )?;
+            close_bundle(ctx.program_id, bundle, ctx.remaining_accounts, sale_manager.seller, false)?;
+        }
+        sale_manager.bundle = Pubkey::default();
+        sale_manager.sale_state = 0;
+        sale_manager.is_auction_mode = false;
+        sale_manager.auction_data = Pubkey::default();
+        sale_manager.sale_pot = Pubkey::default();
+        sale_manager.price = 0;
+        Ok(())
+    }
+
+    pub fn settle_auction<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, SettleAuction<'info>>,
+        ) -> ProgramResult {
+        let sale_manager_info1 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info3 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info4 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info5 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_key = ctx.accounts.sale_manager.key();
+        let sale_pot_key = ctx.accounts.sale_pot.key();
+        let pool = &ctx.accounts.pool;
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        let sale_pot = &mut ctx.accounts.sale_pot;
+        let auction_data = &mut ctx.accounts.auction_data;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
+        let nft_destination_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_destination_token.data.borrow())?;
+        if sale_manager.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if sale_manager.pool != pool.key() {
+            return Err(PoolError::InvalidPoolAccount.into());
+        }
+        if sale_manager.sale_pot != sale_pot_key {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        if sale_pot.pool_pot != *ctx.accounts.manager_pot.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if sale_manager.nft_mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if sale_manager.nft_pot != *ctx.accounts.nft_manager_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_manager_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_destination_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if sale_manager.sale_state != 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        let is_sealed = auction_data.auction_type == AUCTION_TYPE_SEALED_FIRST_PRICE || auction_data.auction_type == AUCTION_TYPE_SEALED_SECOND_PRICE;
+        if auction_data.auction_type != AUCTION_TYPE_ENGLISH && !is_sealed {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
+        let closes_at = if is_sealed { auction_data.reveal_ended_at } else { auction_data.ended_at };
+        if closes_at >= clock.unix_timestamp {
+            return Err(PoolError::InvalidAuctionState.into());
+        }
+        let has_winner = auction_data.auction_state == 2;
+        if has_winner && sale_manager.price < auction_data.reserve_price {
+            return Err(PoolError::ReserveNotMet.into());
+        }
+        // the winner gets the NFT in their associated token account, otherwise it goes back to the seller
+        let receiver = if has_winner { auction_data.last_bidder } else { sale_manager.seller };
+        if has_winner && *ctx.accounts.nft_destination_token.key != get_associated_token_address(&receiver, ctx.accounts.nft_mint.key) {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_destination_token.owner != receiver {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+
+        if has_winner && is_sealed {
+            sale_manager.price = sealed_auction_price(
+                auction_data.auction_type == AUCTION_TYPE_SEALED_SECOND_PRICE,
+                sale_manager.price,
+                auction_data.second_price,
+                auction_data.start_price.max(auction_data.reserve_price),
+            );
+            sale_pot.price = sale_manager.price;
+        }
+
+        let sale_manager_seeds = &[
+            sale_manager.pool.as_ref(),
+            sale_manager.nft_mint.as_ref(),
+            &[sale_manager.bump]
+        ];
+        let bundle = load_bundle(ctx.program_id, sale_manager.bundle, ctx.remaining_accounts)?;
+        let mut fee = 0;
+        if has_winner {
+            let mut item_fees = 0;
+            if let Some(bundle) = &bundle {
+                let (bundle_lead_price, bundle_item_fees) = split_bundle_price(ctx.program_id, bundle, &ctx.remaining_accounts[1..], sale_manager.price, pool.fee_basis_points)?;
+                sale_pot.price = bundle_lead_price;
+                item_fees = bundle_item_fees;
+            }
+            fee = calculate_fee(sale_pot.price, pool.fee_basis_points)?;
+            let total_fee = fee.checked_add(item_fees).ok_or(PoolError::MathOverflow)?;
+            if total_fee > 0 {
+                if *ctx.accounts.fee_pot.key != pool.fee_pot {
+                    return Err(PoolError::InvalidTokenAccount.into());
+                }
+                spl_token_transfer(
+                    TokenTransferParams{
+                        source : ctx.accounts.manager_pot.clone(),
+                        destination : ctx.accounts.fee_pot.clone(),
+                        amount : total_fee,
+                        authority : sale_manager_info4,
+                        authority_signer_seeds : sale_manager_seeds,
+                        token_program : ctx.accounts.token_program.clone(),
+                    }
+                )?;
+            }
+            invoke_signed(
+                &update_primary_sale_happened_via_token(
+                    *ctx.accounts.token_metadata_program.key,
+                    *ctx.accounts.metadata.key,
+                    sale_manager_key,
+                    *ctx.accounts.nft_manager_token.key,
+                ),
+                &[
+                    ctx.accounts.token_metadata_program.clone(),
+                    ctx.accounts.metadata.clone(),
+                    sale_manager_info3,
+                    ctx.accounts.nft_manager_token.clone(),
+                ],
+                &[sale_manager_seeds]
+            )?;
+        }
+        spl_token_transfer(
+            TokenTransferParams{
+                source : ctx.accounts.nft_manager_token.clone(),
+                destination : ctx.accounts.nft_destination_token.clone(),
+                amount : 1,
+                authority : sale_manager_info1,
+                authority_signer_seeds : sale_manager_seeds,
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+
+        if let Some(bundle) = bundle {
+            release_bundle_items(
+                &bundle,
+                &ctx.remaining_accounts[1..],
+                receiver,
+                has_winner,
+                has_winner,
+                sale_manager_info5,
+                sale_manager_seeds,
+                ctx.accounts.token_metadata_program.clone(),
+                ctx.accounts.token_program.clone(),
+            )?;
+            close_bundle(ctx.program_id, bundle, ctx.remaining_accounts, sale_manager.seller, has_winner)?;
+        }
+        sale_manager.bundle = Pubkey::default();
+        if has_winner {
+            auction_data.auction_state = 3;
+            auction_data.locked_amount = sale_manager.price;
+            sale_manager.sale_state = 2;
+            sale_pot.fee = fee;
+            sale_pot.is_used = true;
+        } else {
+            auction_data.auction_state = 4;
+            sale_manager.sale_state = 0;
+        }
+        close_bid_history(ctx.program_id, auction_data, &ctx.accounts.bid_history, &ctx.accounts.seller, sale_manager.seller)?;
+        Ok(())
+    }
+
+    pub fn fail_auction<'a, 'b, 'c, 'info>(
+        ctx : Context<'a, 'b, 'c, 'info, FailAuction<'info>>,
+        ) -> ProgramResult {
+        let sale_manager_info2 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager_info4 = ctx.accounts.sale_manager.to_account_info().clone();
+        let sale_manager = &mut ctx.accounts.sale_manager;
+        let auction_data = &mut ctx.accounts.auction_data;
+        let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+        let nft_manager_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_manager_token.data.borrow())?;
+        let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
+        if sale_manager.auction_data != auction_data.key() {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        if sale_manager.nft_mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if sale_manager.nft_pot != *ctx.accounts.nft_manager_token.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_manager_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.owner != sale_manager.seller {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if sale_manager.sale_state != 1 {
+            return Err(PoolError::InvalidSaleState.into());
+        }
+        let is_sealed = auction_data.auction_type == AUCTION_TYPE_SEALED_FIRST_PRICE || auction_data.auction_type == AUCTION_TYPE_SEALED_SECOND_PRICE;
+        if auction_data.auction_type != AUCTION_TYPE_ENGLISH && !is_sealed {
+            return Err(PoolError::InvalidAuctionMode.into());
+        }
+        let closes_at = if is_sealed { auction_data.reveal_ended_at } else { auction_data.ended_at };
+        if auction_data.auction_state != 2 || closes_at >= clock.unix_timestamp {
+            return Err(PoolError::InvalidAuctionState.into());
+        }
+        if sale_manager.price >= auction_data.reserve_price {
+            return Err(PoolError::ReserveMet.into());
+        }
+
+        let sale_manager_seeds = &[
+            sale_manager.pool.as_ref(),
+            sale_manager.nft_mint.as_ref(),
+            &[sale_manager.bump]
+        ];
+        // bidders get their funds back through withdraw_bid or withdraw_sealed_bid
+        spl_token_transfer(
+            TokenTransferParams{
+                source : ctx.accounts.nft_manager_token.clone(),
+                destination : ctx.accounts.nft_seller_token.clone(),
+                amount : 1,
+                authority : sale_manager_info2,
+                authority_signer_seeds : sale_manager_seeds,
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+
+        if let Some(bundle) = load_bundle(ctx.program_id, sale_manager.bundle, ctx.remaining_accounts)? {
+            release_bundle_items(
+                &bundle,
+                &ctx.remaining_accounts[1..],
+                sale_manager.seller,
+                false,
+                false,
+                sale_manager_info4,
+                sale_manager_seeds,
+                ctx.accounts.token_metadata_program.clone(),
+                ctx.accounts.token_program.clone(),
+            )?;
+            close_bundle(ctx.program_id, bundle, ctx.remaining_accounts, sale_manager.seller, false)?;
+        }
+        sale_manager.bundle = Pubkey::default();
+        auction_data.auction_state = 4;
+        sale_manager.sale_state = 0;
+        close_bid_history(ctx.program_id, auction_data, &ctx.accounts.bid_history, &ctx.accounts.seller, sale_manager.seller)?;
+        Ok(())
+    }
+
+    pub fn make_offer(
+        ctx : Context<MakeOffer>,
+        _price : u64,
+        _bump : u8,
+        ) -> ProgramResult {
+        let pool = &ctx.accounts.pool;
+        let offer = &mut ctx.accounts.offer;
+        let escrow : state::Account = state::Account::unpack_from_slice(&ctx.accounts.escrow.data.borrow())?;
+        let buyer_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.buyer_token.data.borrow())?;
+        if escrow.mint != pool.sale_mint {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if escrow.owner != offer.key() {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if buyer_token.mint != pool.sale_mint {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if _price == 0 {
+            return Err(PoolError::InvalidPrice.into());
+        }
+        if buyer_token.amount < _price {
+            return Err(PoolError::NotEnoughTokenAmount.into());
+        }
+        spl_token_transfer_without_seed(
+            TokenTransferParamsWithoutSeed{
+                source : ctx.accounts.buyer_token.clone(),
+                destination : ctx.accounts.escrow.clone(),
+                amount : _price,
+                authority : ctx.accounts.owner.clone(),
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+        offer.pool = pool.key();
+        offer.buyer = *ctx.accounts.owner.key;
+        offer.scope = OFFER_SCOPE_MINT;
+        offer.target = *ctx.accounts.nft_mint.key;
+        offer.escrow = *ctx.accounts.escrow.key;
+        offer.price = _price;
+        offer.bump = _bump;
+        Ok(())
+    }
+
+    pub fn make_creator_offer(
+        ctx : Context<MakeScopedOffer>,
+        _creator : Pubkey,
+        _price : u64,
+        _bump : u8,
+        ) -> ProgramResult {
+        open_scoped_offer(ctx.accounts, OFFER_SCOPE_CREATOR, _creator, _price, _bump)
+    }
+
+    pub fn make_collection_offer(
+        ctx : Context<MakeScopedOffer>,
+        _collection : Pubkey,
+        _price : u64,
+        _bump : u8,
+        ) -> ProgramResult {
+        open_scoped_offer(ctx.accounts, OFFER_SCOPE_COLLECTION, _collection, _price, _bump)
+    }
+
+    pub fn cancel_offer(
+        ctx : Context<CancelOffer>,
+        ) -> ProgramResult {
+        let offer_info = ctx.accounts.offer.to_account_info().clone();
+        let offer = &ctx.accounts.offer;
+        let escrow : state::Account = state::Account::unpack_from_slice(&ctx.accounts.escrow.data.borrow())?;
+        if offer.escrow != *ctx.accounts.escrow.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        let offer_seeds = &[
+            offer.pool.as_ref(),
+            offer.target.as_ref(),
+            offer.buyer.as_ref(),
+            &[offer.bump]
+        ];
+        spl_token_transfer(
+            TokenTransferParams{
+                source : ctx.accounts.escrow.clone(),
+                destination : ctx.accounts.buyer_token.clone(),
+                amount : escrow.amount,
+                authority : offer_info.clone(),
+                authority_signer_seeds : offer_seeds,
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+        spl_token_close_account(
+            TokenCloseAccountParams{
+                account : ctx.accounts.escrow.clone(),
+                destination : ctx.accounts.owner.clone(),
+                owner : offer_info,
+                owner_signer_seeds : offer_seeds,
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+        Ok(())
+    }
+
+    pub fn accept_offer(
+        ctx : Context<AcceptOffer>,
+        _sale_manager_bump : u8,
+        ) -> ProgramResult {
+        let pool = &ctx.accounts.pool;
+        let offer_info = ctx.accounts.offer.to_account_info().clone();
+        let sale_manager_info = ctx.accounts.sale_manager.clone();
+        let sale_manager_key = *ctx.accounts.sale_manager.key;
+        let sale_manager_bump = load_or_create_sale_manager(
+            ctx.program_id,
+            pool.key(),
+            *ctx.accounts.nft_mint.key,
+            &ctx.accounts.sale_manager,
+            &ctx.accounts.owner,
+            &ctx.accounts.system_program.to_account_info(),
+            _sale_manager_bump,
+        )?;
+        let pool_key = pool.key();
+        let sale_pot = &mut ctx.accounts.sale_pot;
+        let offer = &ctx.accounts.offer;
+        let manager_pot : state::Account = state::Account::unpack_from_slice(&ctx.accounts.manager_pot.data.borrow())?;
+        let nft_seller_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_seller_token.data.borrow())?;
+        let nft_buyer_token : state::Account = state::Account::unpack_from_slice(&ctx.accounts.nft_buyer_token.data.borrow())?;
+        let metadata = load_metadata(&ctx.accounts.metadata, ctx.accounts.nft_mint.key)?;
+        if offer.scope == OFFER_SCOPE_MINT && offer.target != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if offer.escrow != *ctx.accounts.escrow.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if offer.buyer == *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidBidder.into());
+        }
+        if nft_seller_token.owner != *ctx.accounts.owner.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_seller_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_buyer_token.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if nft_buyer_token.owner != offer.buyer {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if manager_pot.mint != pool.sale_mint {
+            return Err(PoolError::InvalidMintAccount.into());
+        }
+        if manager_pot.owner != sale_manager_key {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if metadata.mint != *ctx.accounts.nft_mint.key {
+            return Err(PoolError::InvalidMetadata.into());
+        }
+        if offer.scope == OFFER_SCOPE_CREATOR {
+            let creators = metadata.data.creators.clone().unwrap_or_default();
+            if !creators.iter().any(|c| c.verified && c.address == offer.target) {
+                return Err(PoolError::OfferScopeMismatch.into());
+            }
+        }
+        if offer.scope == OFFER_SCOPE_COLLECTION && read_verified_collection(&ctx.accounts.metadata)? != Some(offer.target) {
+            return Err(PoolError::OfferScopeMismatch.into());
+        }
+
+        // the accepted offer is settled like a sale, withdraw_fund pays the seller and creators
+        sale_pot.sale_manager = sale_manager_key;
+        sale_pot.pool_pot = *ctx.accounts.manager_pot.key;
+        sale_pot.seller = *ctx.accounts.owner.key;
+        sale_pot.price = offer.price;
+        set_royalty(sale_pot, &metadata)?;
+        sale_pot.fee = calculate_fee(offer.price, pool.fee_basis_points)?;
+        sale_pot.is_used = true;
+
+        let offer_seeds = &[
+            offer.pool.as_ref(),
+            offer.target.as_ref(),
+            offer.buyer.as_ref(),
+            &[offer.bump]
+        ];
+        spl_token_transfer(
+            TokenTransferParams{
+                source : ctx.accounts.escrow.clone(),
+                destination : ctx.accounts.manager_pot.clone(),
+                amount : offer.price,
+                authority : offer_info.clone(),
+                authority_signer_seeds : offer_seeds,
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+        // tokens sent to the escrow on top of the offer have no owner to go back to, so the escrow stays open then
+        let escrow : state::Account = state::Account::unpack_from_slice(&ctx.accounts.escrow.data.borrow())?;
+        if escrow.amount == 0 {
+            spl_token_close_account(
+                TokenCloseAccountParams{
+                    account : ctx.accounts.escrow.clone(),
+                    destination : ctx.accounts.buyer.clone(),
+                    owner : offer_info,
+                    owner_signer_seeds : offer_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
+        if sale_pot.fee > 0 {
+            if *ctx.accounts.fee_pot.key != pool.fee_pot {
+                return Err(PoolError::InvalidTokenAccount.into());
+            }
+            let sale_manager_seeds = &[
+                pool_key.as_ref(),
+                ctx.accounts.nft_mint.key.as_ref(),
+                &[sale_manager_bump]
+            ];
+            spl_token_transfer(
+                TokenTransferParams{
+                    source : ctx.accounts.manager_pot.clone(),
+                    destination : ctx.accounts.fee_pot.clone(),
+                    amount : sale_pot.fee,
+                    authority : sale_manager_info,
+                    authority_signer_seeds : sale_manager_seeds,
+                    token_program : ctx.accounts.token_program.clone(),
+                }
+            )?;
+        }
+        invoke(
+            &update_primary_sale_happened_via_token(
+                *ctx.accounts.token_metadata_program.key,
+                *ctx.accounts.metadata.key,
+                *ctx.accounts.owner.key,
+                *ctx.accounts.nft_seller_token.key,
+            ),
+            &[
+                ctx.accounts.token_metadata_program.clone(),
+                ctx.accounts.metadata.clone(),
+                ctx.accounts.owner.clone(),
+                ctx.accounts.nft_seller_token.clone(),
+            ]
+        )?;
+        spl_token_transfer_without_seed(
+            TokenTransferParamsWithoutSeed{
+                source : ctx.accounts.nft_seller_token.clone(),
+                destination : ctx.accounts.nft_buyer_token.clone(),
+                amount : 1,
+                authority : ctx.accounts.owner.clone(),
+                token_program : ctx.accounts.token_program.clone(),
+            }
+        )?;
+        Ok(())
+    }
+
+    // pub fn check_auction_ended(
+    //     ctx : Context<CheckAuctionEnded>,
+    //     ) -> ProgramResult {
+    //     let auction_data = &mut ctx.accounts.auction_data;
+    //     let clock = Clock::from_account_info(&ctx.accounts.clock_sysvar)?;
+    //     if auction_data.ended_at < clock.unix_timestamp {
+    //         auction_data.auction_state = 3;
+    //         sale_pot.is_used = true;
+    //     }
+    //     Ok(())
+    // }
+}
+
+fn record_bid(bid_history : &mut BidHistory, bidder : Pubkey, amount : u64, timestamp : i64) -> ProgramResult {
+    // once full, the oldest record is overwritten
+    let record = BidRecord{
+        bidder,
+        amount,
+        timestamp,
+    };
+    let index = bid_history.total as usize % MAX_BID_HISTORY_NUM;
+    if index < bid_history.bids.len() {
+        bid_history.bids[index] = record;
+    } else {
+        bid_history.bids.push(record);
+    }
+    bid_history.total = bid_history.total.checked_add(1).ok_or(PoolError::MathOverflow)?;
+    Ok(())
+}
+
+// Escrows the price of an offer on every nft of a verified creator or collection
+fn open_scoped_offer(
+    accounts : &mut MakeScopedOffer,
+    scope : u8,
+    target : Pubkey,
+    price : u64,
+    bump : u8,
+    ) -> ProgramResult {
+    let escrow : state::Account = state::Account::unpack_from_slice(&accounts.escrow.data.borrow())?;
+    let buyer_token : state::Account = state::Account::unpack_from_slice(&accounts.buyer_token.data.borrow())?;
+    if escrow.mint != accounts.pool.sale_mint {
+        return Err(PoolError::InvalidMintAccount.into());
+    }
+    if escrow.owner != accounts.offer.key() {
+        return Err(PoolError::InvalidTokenAccount.into());
+    }
+    if buyer_token.mint != accounts.pool.sale_mint {
+        return Err(PoolError::InvalidTokenAccount.into());
+    }
+    if price == 0 {
+        return Err(PoolError::InvalidPrice.into());
+    }
+    if buyer_token.amount < price {
+        return Err(PoolError::NotEnoughTokenAmount.into());
+    }
+    spl_token_transfer_without_seed(
+        TokenTransferParamsWithoutSeed{
+            source : accounts.buyer_token.clone(),
+            destination : accounts.escrow.clone(),
+            amount : price,
+            authority : accounts.owner.clone(),
+            token_program : accounts.token_program.clone(),
+        }
+    )?;
+    let offer = &mut accounts.offer;
+    offer.pool = accounts.pool.key();
+    offer.buyer = *accounts.owner.key;
+    offer.scope = scope;
+    offer.target = target;
+    offer.escrow = *accounts.escrow.key;
+    offer.price = price;
+    offer.bump = bump;
+    Ok(())
+}
+
+fn load_or_create_sale_manager<'info>(
+    program_id : &Pubkey,
+    pool : Pubkey,
+    nft_mint : Pubkey,
+    sale_manager_info : &AccountInfo<'info>,
+    payer : &AccountInfo<'info>,
+    system_program : &AccountInfo<'info>,
+    bump : u8,
+    ) -> std::result::Result<u8, ProgramError> {
+    // accepting an offer does not need a listing first, so the sale manager is created like init_sale_manager does
+    if !sale_manager_info.data_is_empty() {
+        let sale_manager : ProgramAccount<SaleManager> = ProgramAccount::try_from(program_id, sale_manager_info)?;
+        let address = Pubkey::create_program_address(&[pool.as_ref(), nft_mint.as_ref(), &[sale_manager.bump]], program_id)
+            .map_err(|_| PoolError::InvalidSaleManager)?;
+        if address != *sale_manager_info.key {
+            return Err(PoolError::InvalidSaleManager.into());
+        }
+        return Ok(sale_manager.bump);
+    }
+    let sale_manager_seeds = &[pool.as_ref(), nft_mint.as_ref(), &[bump]];
+    let address = Pubkey::create_program_address(sale_manager_seeds, program_id)
+        .map_err(|_| PoolError::InvalidSaleManager)?;
+    if address != *sale_manager_info.key {
+        return Err(PoolError::InvalidSaleManager.into());
+    }
+    let space = 8 + MAX_SALE_MANAGER_SIZE;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            sale_manager_info.key,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            sale_manager_info.clone(),
+            system_program.clone(),
+        ],
+        &[sale_manager_seeds]
+    )?;
+    let sale_manager = SaleManager{
+        pool,
+        seller : Pubkey::default(),
+        nft_mint,
+        nft_pot : Pubkey::default(),
+        sale_pot : Pubkey::default(),
+        price : 0,
+        sale_state : 0,
+        is_primary : false,
+        is_auction_mode : false,
+        auction_data : Pubkey::default(),
+        bump,
+        starts_at : 0,
+        expires_at : 0,
+        reserved_buyer : Pubkey::default(),
+        bundle : Pubkey::default(),
+        is_delegated : false,
+    };
+    let mut data = sale_manager_info.try_borrow_mut_data()?;
+    let mut cursor : &mut [u8] = &mut data;
+    sale_manager.try_serialize(&mut cursor)?;
+    Ok(bump)
+}
+
+fn close_bid_history<'info>(
+    program_id : &Pubkey,
+    auction_data : &AuctionData,
+    bid_history_info : &AccountInfo<'info>,
+    seller_info : &AccountInfo<'info>,
+    seller : Pubkey,
+    ) -> ProgramResult {
+    // only english auctions have a bid history, the others pass auction_data.bid_history which is unset
+    if auction_data.auction_type != AUCTION_TYPE_ENGLISH {
+        return Ok(());
+    }
+    if *bid_history_info.key != auction_data.bid_history {
+        return Err(PoolError::InvalidBidHistory.into());
+    }
+    if *seller_info.key != seller {
+        return Err(PoolError::InvalidSeller.into());
+    }
+    let bid_history : ProgramAccount<BidHistory> = ProgramAccount::try_from(program_id, bid_history_info)?;
+    bid_history.close(seller_info.clone())
+}
+
+fn check_lot_open(program_id : &Pubkey, sale_manager : &SaleManager, auction_data : &AccountInfo) -> ProgramResult {
+    // a lot can only change while nobody has bid on it
+    if sale_manager.sale_state != 1 {
+        return Err(PoolError::InvalidSaleState.into());
+    }
+    if sale_manager.is_auction_mode {
+        if sale_manager.auction_data != *auction_data.key {
+            return Err(PoolError::InvalidAuctionDataAccount.into());
+        }
+        let auction_data : ProgramAccount<AuctionData> = ProgramAccount::try_from(program_id, auction_data)?;
+        if auction_data.auction_state != 1 || auction_data.commit_count != 0 {
+            return Err(PoolError::InvalidAuctionState.into());
+        }
+    }
+    Ok(())
+}
+
+fn load_bundle<'info>(
+    program_id : &Pubkey,
+    bundle_key : Pubkey,
+    remaining_accounts : &[AccountInfo<'info>],
+    ) -> std::result::Result<Option<ProgramAccount<'info, Bundle>>, ProgramError> {
+    // remaining accounts : bundle, then BUNDLE_ITEM_ACCOUNT_NUM accounts per item in bundle order, then the seller wallet
+    if bundle_key == Pubkey::default() {
+        return Ok(None);
+    }
+    if remaining_accounts.is_empty() || *remaining_accounts[0].key != bundle_key {
+        return Err(PoolError::InvalidBundle.into());
+    }
+    let bundle : ProgramAccount<Bundle> = ProgramAccount::try_from(program_id, &remaining_accounts[0])?;
+    if remaining_accounts.len() != 2 + BUNDLE_ITEM_ACCOUNT_NUM * bundle.items.len() {
+        return Err(PoolError::InvalidRemainingAccounts.into());
+    }
+    Ok(Some(bundle))
+}
+
+fn split_bundle_price(
+    program_id : &Pubkey,
+    bundle : &Bundle,
+    item_accounts : &[AccountInfo],
+    price : u64,
+    fee_basis_points : u16,
+    ) -> std::result::Result<(u64, u64), ProgramError> {
+    let (lead_price, item_price) = bundle_prices(price, bundle.items.len());
+    let item_fee = calculate_fee(item_price, fee_basis_points)?;
+    for (i, item) in bundle.items.iter().enumerate() {
+        let sale_pot_info = &item_accounts[i * BUNDLE_ITEM_ACCOUNT_NUM];
+        if *sale_pot_info.key != item.sale_pot {
+            return Err(PoolError::InvalidSalePotAccount.into());
+        }
+        let mut sale_pot : ProgramAccount<SalePot> = ProgramAccount::try_from(program_id, sale_pot_info)?;
+        sale_pot.price = item_price;
+        sale_pot.fee = item_fee;
+        sale_pot.is_used = true;
+        sale_pot.exit(program_id)?;
+    }
+    let item_fees = item_fee.checked_mul(bundle.items.len() as u64).ok_or(PoolError::MathOverflow)?;
+    Ok((lead_price, item_fees))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn release_bundle_items<'info>(
+    bundle : &Bundle,
+    item_accounts : &[AccountInfo<'info>],
+    receiver : Pubkey,
+    is_sale : bool,
+    require_associated_token : bool,
+    sale_manager_info : AccountInfo<'info>,
+    sale_manager_seeds : &[&[u8]],
+    token_metadata_program : AccountInfo<'info>,
+    token_program : AccountInfo<'info>,
+    ) -> ProgramResult {
+    for (i, item) in bundle.items.iter().enumerate() {
+        let metadata_info = &item_accounts[i * BUNDLE_ITEM_ACCOUNT_NUM + 1];
+        let nft_pot_info = &item_accounts[i * BUNDLE_ITEM_ACCOUNT_NUM + 2];
+        let destination_info = &item_accounts[i * BUNDLE_ITEM_ACCOUNT_NUM + 3];
+        if *nft_pot_info.key != item.nft_pot {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if *destination_info.owner != spl_token::id() {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        let destination : state::Account = state::Account::unpack_from_slice(&destination_info.data.borrow())?;
+        if destination.mint != item.nft_mint || destination.owner != receiver {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        if require_associated_token && *destination_info.key != get_associated_token_address(&receiver, &item.nft_mint) {
+            return Err(PoolError::InvalidTokenAccount.into());
+        }
+        let metadata : metaplex_token_metadata::state::Metadata = metaplex_token_metadata::state::Metadata::from_account_info(metadata_info)?;
+        if metadata.mint != item.nft_mint {
+            return Err(PoolError::InvalidMetadata.into());
+        }
+
+        if is_sale {
+            invoke_signed(
+                &update_primary_sale_happened_via_token(
+                    *token_metadata_program.key,
+                    *metadata_info.key,
+                    *sale_manager_info.key,
+                    *nft_pot_info.key,
+                ),
+                &[
+                    token_metadata_program.clone(),
+                    metadata_info.clone(),
+                    sale_manager_info.clone(),
+                    nft_pot_info.clone(),
+                ],
+                &[sale_manager_seeds]
+            )?;
+        }
+        spl_token_transfer(
+            TokenTransferParams{
+                source : nft_pot_info.clone(),
+                destination : destination_info.clone(),
+                amount : 1,
+                authority : sale_manager_info.clone(),
+                authority_signer_seeds : sale_manager_seeds,
+                token_program : token_program.clone(),
+            }
+        )?;
+    }
+    Ok(())
+}
+
+fn close_bundle<'info>(
+    program_id : &Pubkey,
+    bundle : ProgramAccount<'info, Bundle>,
+    remaining_accounts : &[AccountInfo<'info>],
+    seller : Pubkey,
+    is_sale : bool,
+    ) -> ProgramResult {
+    // the seller paid rent for the bundle and the item sale pots. sold items keep their pots until paid out
+    let seller_info = &remaining_accounts[remaining_accounts.len() - 1];
+    if *seller_info.key != seller {
+        return Err(PoolError::InvalidSeller.into());
+    }
+    if !is_sale {
+        for (i, item) in bundle.items.iter().enumerate() {
+            let sale_pot_info = &remaining_accounts[1 + i * BUNDLE_ITEM_ACCOUNT_NUM];
+            if *sale_pot_info.key != item.sale_pot {
+                return Err(PoolError::InvalidSalePotAccount.into());
+            }
+            let sale_pot : ProgramAccount<SalePot> = ProgramAccount::try_from(program_id, sale_pot_info)?;
+            sale_pot.close(seller_info.clone())?;
+        }
+    }
+    bundle.close(seller_info.clone())?;
+    Ok(())
+}
+
+fn is_delegate_listing_stale(sale_manager_key : Pubkey, sale_manager : &SaleManager, nft_seller_token : &state::Account) -> bool {
+    nft_seller_token.owner != sale_manager.seller
+        || nft_seller_token.mint != sale_manager.nft_mint
+        || nft_seller_token.amount != 1
+        || nft_seller_token.delegate != COption::Some(sale_manager_key)
+        || nft_seller_token.delegated_amount < 1
+}
+
+fn revoke_delegation<'info>(
+    sale_manager_key : Pubkey,
+    sale_manager : &SaleManager,
+    nft_seller_token_info : &AccountInfo<'info>,
+    seller : &AccountInfo<'info>,
+    token_program : &AccountInfo<'info>,
+    ) -> ProgramResult {
+    // only the token owner can revoke, so without the seller's signature the approval stays.
+    // it can not be used : the sale manager only moves a delegated nft in buy_nft on an open listing
+    if !seller.is_signer || *seller.key != sale_manager.seller || *nft_seller_token_info.owner != spl_token::id() {
+        return Ok(());
+    }
+    let nft_seller_token = match state::Account::unpack_from_slice(&nft_seller_token_info.data.borrow()) {
+        Ok(nft_seller_token) => nft_seller_token,
+        Err(_) => return Ok(()),
+    };
+    if nft_seller_token.owner != sale_manager.seller || nft_seller_token.delegate != COption::Some(sale_manager_key) {
+        return Ok(());
+    }
+    spl_token_revoke(
+        TokenRevokeParams{
+            source : nft_seller_token_info.clone(),
+            owner : seller.clone(),
+            token_program : token_program.clone(),
+        }
+    )
+}
+
+// Lists the seller's nft on an auction : checks the nft and pot accounts, opens the sale
+// manager and sale pot and moves the nft into escrow. Returns when the auction starts.
+fn open_auction_listing(
+    accounts : &mut SellNftByAuction,
+    price : u64,
+    starts_at : i64,
+    ) -> std::result::Result<i64, ProgramError> {
+    let sale_manager_key = accounts.sale_manager.key();
+    let manager_pot : state::Account = state::Account::unpack_from_slice(&accounts.manager_pot.data.borrow())?;
+    let nft_seller_token : state::Account = state::Account::unpack_from_slice(&accounts.nft_seller_token.data.borrow())?;
+    let nft_manager_token : state::Account = state::Account::unpack_from_slice(&accounts.nft_manager_token.data.borrow())?;
+    let metadata = load_metadata(&accounts.metadata, accounts.nft_mint.key)?;
+    let clock = Clock::from_account_info(&accounts.clock_sysvar)?;
+    if accounts.sale_manager.sale_state == 1 {
+        return Err(PoolError::InvalidSaleState.into());
+    }
+    if nft_seller_token.owner != *accounts.owner.key {
+        return Err(PoolError::InvalidTokenAccount.into());
+    }
+    if nft_seller_token.mint != *accounts.nft_mint.key {
+        return Err(PoolError::InvalidTokenAccount.into());
+    }
+    if nft_manager_token.mint != *accounts.nft_mint.key {
+        return Err(PoolError::InvalidTokenAccount.into());
+    }
+    if manager_pot.mint != accounts.pool.sale_mint {
+        return Err(PoolError::InvalidMintAccount.into());
+    }
+    if manager_pot.owner != sale_manager_key {
+        return Err(PoolError::InvalidTokenAccount.into());
+    }
+    if metadata.mint != *accounts.nft_mint.key {
+        return Err(PoolError::InvalidMetadata.into());
+    }
+
+    let sale_manager = &mut accounts.sale_manager;
+    sale_manager.sale_pot = accounts.sale_pot.key();
+    sale_manager.auction_data = accounts.auction_data.key();
+    sale_manager.seller = *accounts.owner.key;
+    sale_manager.price = price;
+    sale_manager.sale_state = 1;
+    sale_manager.nft_pot = *accounts.nft_manager_token.key;
+    sale_manager.is_auction_mode = true;
+    sale_manager.is_delegated = false;
+    sale_manager.starts_at = starts_at.max(clock.unix_timestamp);
+    sale_manager.expires_at = 0;
+    sale_manager.reserved_buyer = Pubkey::default();
+
+    accounts.auction_data.pool_pot = *accounts.manager_pot.key;
+
+    let sale_pot = &mut accounts.sale_pot;
+    sale_pot.sale_manager = sale_manager_key;
+    sale_pot.is_used = false;
+    sale_pot.price = price;
+    sale_pot.pool_pot = *accounts.manager_pot.key;
+    sale_pot.seller = *accounts.owner.key;
+    set_royalty(sale_pot, &metadata)?;
+    spl_token_transfer_without_seed(
+        TokenTransferParamsWithoutSeed{
+            source : accounts.nft_seller_token.clone(),
+            destination : accounts.nft_manager_token.clone(),
+            amount : 1,
+            authority : accounts.owner.clone(),
+            token_program : accounts.token_program.clone(),
+        }
+    )?;
+    Ok(sale_manager.starts_at)
+}
+
+fn load_metadata(
+    metadata_info : &AccountInfo,
+    nft_mint : &Pubkey,
+    ) -> std::result::Result<metaplex_token_metadata::state::Metadata, ProgramError> {
+    // royalties are snapshotted from this account, so it has to be the mint's real metadata
+    if *metadata_info.owner != metaplex_token_metadata::id() {
+        return Err(PoolError::InvalidMetadata.into());
+    }
+    let (metadata_key, _) = Pubkey::find_program_address(
+        &[
+            metaplex_token_metadata::state::PREFIX.as_bytes(),
+            metaplex_token_metadata::id().as_ref(),
+            nft_mint.as_ref(),
+        ],
+        &metaplex_token_metadata::id(),
+    );
+    if *metadata_info.key != metadata_key {
+        return Err(PoolError::InvalidMetadata.into());
+    }
+    let metadata = metaplex_token_metadata::state::Metadata::from_account_info(metadata_info)?;
+    Ok(metadata)
+}
+
+fn read_verified_collection(metadata_info : &AccountInfo) -> std::result::Result<Option<Pubkey>, ProgramError> {
+    // metaplex-token-metadata 0.0.1 stops at edition_nonce. later metadata program versions write token_standard
+    // and collection into the padding after it, and metadata written before them leaves that padding zeroed
+    let data = metadata_info.data.borrow();
+    let mut rest : &[u8] = &data;
+    metaplex_token_metadata::state::Metadata::deserialize(&mut rest)?;
+    let _token_standard : Option<u8> = BorshDeserialize::deserialize(&mut rest)?;
+    let collection : Option<(bool, Pubkey)> = BorshDeserialize::deserialize(&mut rest)?;
+    Ok(collection.filter(|(verified, _)| *verified).map(|(_, key)| key))
+}
+
+fn set_royalty(
+    sale_pot : &mut SalePot,
+    metadata : &metaplex_token_metadata::state::Metadata,
+    ) -> ProgramResult {
+    if metadata.data.seller_fee_basis_points > 10000 {
+        return Err(PoolError::InvalidRoyalty.into());
+    }
+    sale_pot.is_primary = !metadata.primary_sale_happened;
+    sale_pot.seller_fee_basis_points = metadata.data.seller_fee_basis_points;
+    sale_pot.creators = Vec::new();
+    if let Some(creators) = &metadata.data.creators {
+        if creators.len() > MAX_CREATOR_NUM {
+            return Err(PoolError::InvalidRoyalty.into());
+        }
+        for c in creators {
+            sale_pot.creators.push(Creator{
+                address : c.address,
+                verified : false,
+                share : c.share,
+            })
+        }
+    }
+    // on a primary sale creators share the whole price, unless there are none
+    sale_pot.seller_verified = sale_pot.is_primary && !sale_pot.creators.is_empty();
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(_price : u64, _bump : u8)]
+pub struct MakeOffer<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    #[account(init,seeds=[pool.key().as_ref(),(*nft_mint.key).as_ref(),owner.key.as_ref()],bump=_bump,payer=owner,space=8+OFFER_SIZE)]
+    offer : ProgramAccount<'info,Offer>,
+
+    #[account(mut,owner=spl_token::id())]
+    escrow : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    buyer_token : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    system_program : Program<'info,System>,
+}
+
+#[derive(Accounts)]
+#[instruction(_target : Pubkey, _price : u64, _bump : u8)]
+pub struct MakeScopedOffer<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(init,seeds=[pool.key().as_ref(),_target.as_ref(),owner.key.as_ref()],bump=_bump,payer=owner,space=8+OFFER_SIZE)]
+    offer : ProgramAccount<'info,Offer>,
+
+    #[account(mut,owner=spl_token::id())]
+    escrow : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    buyer_token : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    system_program : Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOffer<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(mut,seeds=[offer.pool.as_ref(),offer.target.as_ref(),owner.key.as_ref()],bump=offer.bump,close=owner)]
+    offer : ProgramAccount<'info,Offer>,
+
+    #[account(mut,owner=spl_token::id())]
+    escrow : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    buyer_token : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOffer<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(mut,address=offer.buyer)]
+    buyer : AccountInfo<'info>,
+
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    #[account(mut)]
+    metadata : AccountInfo<'info>,
+
+    #[account(mut)]
+    sale_manager : AccountInfo<'info>,
+
+    #[account(init,payer=owner,space=8+SALE_POT_SIZE)]
+    sale_pot : ProgramAccount<'info,SalePot>,
+
+    #[account(mut,seeds=[pool.key().as_ref(),offer.target.as_ref(),offer.buyer.as_ref()],bump=offer.bump,close=buyer)]
+    offer : ProgramAccount<'info,Offer>,
+
+    #[account(mut,owner=spl_token::id())]
+    escrow : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
+
+    #[account(mut)]
+    fee_pot : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_seller_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_buyer_token : AccountInfo<'info>,
+
+    #[account(address=metaplex_token_metadata::id())]
+    token_metadata_program : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    system_program : Program<'info,System>,
+}
+
+// #[derive(Accounts)]
+// pub struct CheckAuctionEnded<'info> {
+//     #[account(mut,signer)]
+//     owner : AccountInfo<'info>,
+
+//     #[account(mut)]
+//     auction_data : ProgramAccount<'info,AuctionData>,
+
+//     clock_sysvar : AccountInfo<'info>,    
+// }
+
+#[derive(Accounts)]
+#[instruction(_bump : u8)]
+pub struct InitBidReceipt<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    #[account(init,seeds=[auction_data.key().as_ref(),owner.key.as_ref()],bump=_bump,payer=owner,space=8+BID_RECEIPT_SIZE)]
+    bid_receipt : ProgramAccount<'info,BidReceipt>,
+
+    system_program : Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBid<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    #[account(mut,seeds=[auction_data.key().as_ref(),owner.key.as_ref()],bump=bid_receipt.bump)]
+    bid_receipt : ProgramAccount<'info,BidReceipt>,
+
+    #[account(mut,owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    bidder_token : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_commitment : [u8; 32], _collateral : u64, _bump : u8)]
+pub struct CommitBid<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(seeds=[pool.key().as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    #[account(init,seeds=[auction_data.key().as_ref(),owner.key.as_ref()],bump=_bump,payer=owner,space=8+SEALED_BID_SIZE)]
+    sealed_bid : ProgramAccount<'info,SealedBid>,
+
+    #[account(mut,owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    bidder_token : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    system_program : Program<'info,System>,
+
+    clock_sysvar : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealBid<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(mut,seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    #[account(mut,seeds=[auction_data.key().as_ref(),owner.key.as_ref()],bump=sealed_bid.bump)]
+    sealed_bid : ProgramAccount<'info,SealedBid>,
+
+    clock_sysvar : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSealedBid<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    #[account(mut,seeds=[auction_data.key().as_ref(),owner.key.as_ref()],bump=sealed_bid.bump,close=owner)]
+    sealed_bid : ProgramAccount<'info,SealedBid>,
+
+    #[account(mut,owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    bidder_token : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    clock_sysvar : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAuction<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    #[account(mut)]
+    metadata : AccountInfo<'info>,
+
+    #[account(mut,seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut,close=owner)]
+    sale_pot : ProgramAccount<'info,SalePot>,
+
+    #[account(mut,close=owner)]
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    bid_history : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_seller_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_manager_token : AccountInfo<'info>,
+
+    #[account(address=metaplex_token_metadata::id())]
+    token_metadata_program : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    #[account(mut)]
+    metadata : AccountInfo<'info>,
+
+    #[account(mut,seeds=[pool.key().as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    sale_pot : ProgramAccount<'info,SalePot>,
+
+    #[account(mut)]
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    bid_history : AccountInfo<'info>,
+
+    #[account(mut)]
+    seller : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_manager_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_destination_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
+
+    #[account(mut)]
+    fee_pot : AccountInfo<'info>,
+
+    #[account(address=metaplex_token_metadata::id())]
+    token_metadata_program : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    clock_sysvar : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FailAuction<'info> {
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    #[account(mut)]
+    metadata : AccountInfo<'info>,
+
+    #[account(mut,seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    auction_data : ProgramAccount<'info,AuctionData>,
+
+    bid_history : AccountInfo<'info>,
+
+    #[account(mut)]
+    seller : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_manager_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_seller_token : AccountInfo<'info>,
+
+    #[account(address=metaplex_token_metadata::id())]
+    token_metadata_program : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    clock_sysvar : AccountInfo<'info>,
+}
 
 #[derive(Accounts)]
 pub struct ClaimBid<'info> {
     #[account(mut,signer)]
     owner : AccountInfo<'info>,
 
+    pool : ProgramAccount<'info,Pool>,
+
     #[account(owner=spl_token::id())]
     nft_mint : AccountInfo<'info>,
 
@@ -806,11 +3201,22 @@ pub struct ClaimBid<'info> {
     #[account(mut)]
     auction_data : ProgramAccount<'info,AuctionData>,
 
+    bid_history : AccountInfo<'info>,
+
+    #[account(mut)]
+    seller : AccountInfo<'info>,
+
     #[account(mut,owner=spl_token::id())]
     nft_manager_token : AccountInfo<'info>,
 
-    #[account(mut,owner=spl_token::id())]
-    nft_bidder_token : AccountInfo<'info>,
+    #[account(mut,owner=spl_token::id())]
+    nft_bidder_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
+
+    #[account(mut)]
+    fee_pot : AccountInfo<'info>,
 
     #[account(address=metaplex_token_metadata::id())]
     token_metadata_program : AccountInfo<'info>,
@@ -843,8 +3249,11 @@ pub struct PlaceBid<'info> {
     #[account(mut,owner=spl_token::id())]
     bidder_token : AccountInfo<'info>,
 
+    #[account(mut,seeds=[auction_data.key().as_ref(),owner.key.as_ref()],bump=bid_receipt.bump)]
+    bid_receipt : ProgramAccount<'info,BidReceipt>,
+
     #[account(mut)]
-    prev_bidder_token : AccountInfo<'info>,
+    bid_history : ProgramAccount<'info,BidHistory>,
 
     #[account(address=metaplex_token_metadata::id())]
     token_metadata_program : AccountInfo<'info>,
@@ -855,6 +3264,16 @@ pub struct PlaceBid<'info> {
     clock_sysvar : AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct SellNftByEnglishAuction<'info> {
+    listing : SellNftByAuction<'info>,
+
+    system_program : Program<'info,System>,
+
+    #[account(init,payer=listing.owner,space=8+BID_HISTORY_SIZE)]
+    bid_history : ProgramAccount<'info,BidHistory>,
+}
+
 #[derive(Accounts)]
 pub struct SellNftByAuction<'info> {
     #[account(mut,signer)]
@@ -865,7 +3284,6 @@ pub struct SellNftByAuction<'info> {
     #[account(owner=spl_token::id())]
     nft_mint : AccountInfo<'info>,
 
-    #[account(mut)]
     metadata : AccountInfo<'info>,
 
     #[account(mut,seeds=[pool.key().as_ref(),(*nft_mint.key).as_ref()],bump=sale_manager.bump)]
@@ -886,9 +3304,6 @@ pub struct SellNftByAuction<'info> {
     #[account(mut,owner=spl_token::id())]
     manager_pot : AccountInfo<'info>,
 
-    #[account(address=metaplex_token_metadata::id())]
-    token_metadata_program : AccountInfo<'info>,
-
     #[account(address=spl_token::id())]
     token_program : AccountInfo<'info>,
 
@@ -897,6 +3312,21 @@ pub struct SellNftByAuction<'info> {
     clock_sysvar : AccountInfo<'info>,    
 }
 
+#[derive(Accounts)]
+pub struct SettleSale<'info> {
+    #[account(seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    sale_pot : ProgramAccount<'info,SalePot>,
+
+    #[account(mut,owner=spl_token::id())]
+    pool_pot : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct WithdrawFund<'info> {
     #[account(mut,signer)]
@@ -918,6 +3348,62 @@ pub struct WithdrawFund<'info> {
     token_program : AccountInfo<'info>,     
 }
 
+#[derive(Accounts)]
+pub struct UpdateListingPrice<'info> {
+    #[account(signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(mut,seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    sale_pot : ProgramAccount<'info,SalePot>,
+}
+
+#[derive(Accounts)]
+pub struct DelistStale<'info> {
+    seller : AccountInfo<'info>,
+
+    #[account(mut,seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    nft_seller_token : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireListing<'info> {
+    seller : AccountInfo<'info>,
+
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    #[account(mut)]
+    metadata : AccountInfo<'info>,
+
+    #[account(mut,seeds=[pool.key().as_ref(),(*nft_mint.key).as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_seller_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_manager_token : AccountInfo<'info>,
+
+    #[account(address=metaplex_token_metadata::id())]
+    token_metadata_program : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    clock_sysvar : AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct RedeemNft<'info> {
     #[account(mut,signer)]
@@ -978,6 +3464,9 @@ pub struct BuyNft<'info> {
     #[account(mut,owner=spl_token::id())]
     bidder_token : AccountInfo<'info>,
 
+    #[account(mut)]
+    fee_pot : AccountInfo<'info>,
+
     #[account(address=metaplex_token_metadata::id())]
     token_metadata_program : AccountInfo<'info>,
 
@@ -985,6 +3474,62 @@ pub struct BuyNft<'info> {
     token_program : AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct InitBundle<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(mut,seeds=[sale_manager.pool.as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(init,payer=owner,space=8+BUNDLE_SIZE)]
+    bundle : ProgramAccount<'info,Bundle>,
+
+    auction_data : AccountInfo<'info>,
+
+    system_program : Program<'info,System>,
+}
+
+#[derive(Accounts)]
+pub struct AddBundleItem<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(seeds=[pool.key().as_ref(),sale_manager.nft_mint.as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(mut)]
+    bundle : ProgramAccount<'info,Bundle>,
+
+    lead_sale_pot : ProgramAccount<'info,SalePot>,
+
+    auction_data : AccountInfo<'info>,
+
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    metadata : AccountInfo<'info>,
+
+    #[account(init,payer=owner,space=8+SALE_POT_SIZE)]
+    sale_pot : ProgramAccount<'info,SalePot>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_seller_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_bundle_token : AccountInfo<'info>,
+
+    #[account(owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    system_program : Program<'info,System>,
+}
+
 #[derive(Accounts)]
 pub struct SellNft<'info> {
     #[account(mut,signer)]
@@ -995,7 +3540,6 @@ pub struct SellNft<'info> {
     #[account(owner=spl_token::id())]
     nft_mint : AccountInfo<'info>,
 
-    #[account(mut)]
     metadata : AccountInfo<'info>,
 
     #[account(mut,seeds=[pool.key().as_ref(),(*nft_mint.key).as_ref()],bump=sale_manager.bump)]
@@ -1013,13 +3557,44 @@ pub struct SellNft<'info> {
     #[account(mut,owner=spl_token::id())]
     manager_pot : AccountInfo<'info>,
 
-    #[account(address=metaplex_token_metadata::id())]
-    token_metadata_program : AccountInfo<'info>,
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+
+    system_program : Program<'info,System>,
+
+    clock_sysvar : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SellNftByDelegate<'info> {
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(owner=spl_token::id())]
+    nft_mint : AccountInfo<'info>,
+
+    metadata : AccountInfo<'info>,
+
+    #[account(mut,seeds=[pool.key().as_ref(),(*nft_mint.key).as_ref()],bump=sale_manager.bump)]
+    sale_manager : ProgramAccount<'info,SaleManager>,
+
+    #[account(init,payer=owner,space=8+SALE_POT_SIZE)]
+    sale_pot : ProgramAccount<'info,SalePot>,
+
+    #[account(mut,owner=spl_token::id())]
+    nft_seller_token : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    manager_pot : AccountInfo<'info>,
 
     #[account(address=spl_token::id())]
     token_program : AccountInfo<'info>,
 
     system_program : Program<'info,System>,
+
+    clock_sysvar : AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
@@ -1081,6 +3656,52 @@ pub struct SetAuthority<'info>{
     new_owner : AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct SetPoolLimits<'info>{
+    #[account(mut, has_one=owner)]
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_fee_basis_points : u16, _bump : u8)]
+pub struct SetFee<'info>{
+    #[account(mut, has_one=owner)]
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(seeds=[pool.key().as_ref(),TREASURY.as_bytes()],bump=_bump)]
+    treasury : AccountInfo<'info>,
+
+    #[account(owner=spl_token::id())]
+    fee_pot : AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFee<'info>{
+    #[account(has_one=owner)]
+    pool : ProgramAccount<'info,Pool>,
+
+    #[account(mut,signer)]
+    owner : AccountInfo<'info>,
+
+    #[account(seeds=[pool.key().as_ref(),TREASURY.as_bytes()],bump=pool.treasury_bump)]
+    treasury : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    fee_pot : AccountInfo<'info>,
+
+    #[account(mut,owner=spl_token::id())]
+    withdraw_pot : AccountInfo<'info>,
+
+    #[account(address=spl_token::id())]
+    token_program : AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct InitPool<'info>{
     #[account(init, payer=owner, space=8+POOL_SIZE)]
@@ -1099,6 +3720,13 @@ pub struct InitPool<'info>{
 pub struct Pool{
     pub owner : Pubkey,
     pub sale_mint : Pubkey,
+    pub fee_basis_points : u16,
+    pub fee_pot : Pubkey,
+    pub treasury_bump : u8,
+    pub max_extension_window : i64,
+    pub max_extension : i64,
+    pub max_gap_tick_percentage : u8,
+    pub max_min_increment : u64,
 }
 
 
@@ -1133,6 +3761,11 @@ pub struct SaleManager{
     pub is_auction_mode : bool,
     pub auction_data : Pubkey,
     pub bump : u8,
+    pub starts_at : i64,
+    pub expires_at : i64,
+    pub reserved_buyer : Pubkey,
+    pub bundle : Pubkey,
+    pub is_delegated : bool,
 }
 
 #[account]
@@ -1145,9 +3778,23 @@ pub struct SalePot{
     pub seller : Pubkey,
     pub seller_verified : bool,
     pub seller_fee_basis_points : u16,
+    pub fee : u64,
     pub creators : Vec<Creator>,
 }
 
+#[derive(AnchorSerialize,AnchorDeserialize,Clone)]
+pub struct BundleItem {
+    pub nft_mint : Pubkey,
+    pub nft_pot : Pubkey,
+    pub sale_pot : Pubkey,
+}
+
+#[account]
+pub struct Bundle{
+    pub sale_manager : Pubkey,
+    pub items : Vec<BundleItem>,
+}
+
 #[account]
 pub struct AuctionData{
     pub ended_at : i64,
@@ -1155,6 +3802,73 @@ pub struct AuctionData{
     pub last_bidder_token : Pubkey,
     pub auction_state : u8,
     pub gap_tick_percentage : u8,
+    pub auction_type : u8,
+    pub started_at : i64,
+    pub start_price : u64,
+    pub end_price : u64,
+    pub price_drop_interval : i64,
+    pub extension_window : i64,
+    pub extension : i64,
+    pub reserve_price : u64,
+    pub reveal_ended_at : i64,
+    pub second_price : u64,
+    pub commit_count : u32,
+    pub buy_now_price : u64,
+    pub min_increment : u64,
+    pub last_bid_receipt : Pubkey,
+    pub locked_amount : u64,
+    // the leader's proxy maximum, readable by anyone like the receipt escrow backing it
+    pub leader_max_price : u64,
+    pub bid_history : Pubkey,
+    // pot holding this auction's bids, kept after the sale manager is relisted
+    pub pool_pot : Pubkey,
+}
+
+#[account]
+pub struct SealedBid{
+    pub auction_data : Pubkey,
+    pub bidder : Pubkey,
+    pub bidder_token : Pubkey,
+    pub commitment : [u8; 32],
+    pub collateral : u64,
+    pub price : u64,
+    pub is_revealed : bool,
+    pub bump : u8,
+}
+
+#[account]
+pub struct BidReceipt{
+    pub auction_data : Pubkey,
+    pub sale_manager : Pubkey,
+    pub bidder : Pubkey,
+    pub amount : u64,
+    pub bump : u8,
+}
+
+#[account]
+pub struct Offer{
+    pub pool : Pubkey,
+    pub buyer : Pubkey,
+    pub scope : u8,
+    // nft mint, verified creator or verified collection mint, depending on scope
+    pub target : Pubkey,
+    pub escrow : Pubkey,
+    pub price : u64,
+    pub bump : u8,
+}
+
+#[derive(AnchorSerialize,AnchorDeserialize,Clone)]
+pub struct BidRecord {
+    pub bidder : Pubkey,
+    pub amount : u64,
+    pub timestamp : i64,
+}
+
+#[account]
+pub struct BidHistory{
+    pub auction_data : Pubkey,
+    pub total : u32,
+    pub bids : Vec<BidRecord>,
 }
 
 #[error]
@@ -1224,4 +3938,88 @@ pub enum PoolError {
 
     #[msg("Invalid auction state")]
     InvalidAuctionState,
+
+    #[msg("Invalid fee")]
+    InvalidFee,
+
+    #[msg("Invalid sale pot account")]
+    InvalidSalePotAccount,
+
+    #[msg("Math overflow")]
+    MathOverflow,
+
+    #[msg("Invalid metadata")]
+    InvalidMetadata,
+
+    #[msg("Invalid royalty")]
+    InvalidRoyalty,
+
+    #[msg("Invalid remaining accounts")]
+    InvalidRemainingAccounts,
+
+    #[msg("Invalid auction schedule")]
+    InvalidAuctionSchedule,
+
+    #[msg("Invalid extension")]
+    InvalidExtension,
+
+    #[msg("Reserve price not met")]
+    ReserveNotMet,
+
+    #[msg("Reserve price met")]
+    ReserveMet,
+
+    #[msg("Already revealed")]
+    AlreadyRevealed,
+
+    #[msg("Invalid commitment")]
+    InvalidCommitment,
+
+    #[msg("Sale not started")]
+    NotStarted,
+
+    #[msg("Invalid bid increment")]
+    InvalidBidIncrement,
+
+    #[msg("Invalid bid receipt")]
+    InvalidBidReceipt,
+
+    #[msg("Invalid bid history")]
+    InvalidBidHistory,
+
+    #[msg("Price mismatch")]
+    PriceMismatch,
+
+    #[msg("Exceeds max spend")]
+    ExceedsMaxSpend,
+
+    #[msg("Listing expired")]
+    ListingExpired,
+
+    #[msg("Listing not expired")]
+    ListingNotExpired,
+
+    #[msg("Not reserved buyer")]
+    NotReservedBuyer,
+
+    #[msg("Invalid bundle")]
+    InvalidBundle,
+
+    #[msg("Nft does not match offer scope")]
+    OfferScopeMismatch,
+
+    #[msg("Token approve failed")]
+    TokenApproveFailed,
+
+    #[msg("Token revoke failed")]
+    TokenRevokeFailed,
+
+    #[msg("Stale listing")]
+    StaleListing,
+
+    #[msg("Token close account failed")]
+    TokenCloseAccountFailed,
+
+    #[msg("Invalid sale manager")]
+    InvalidSaleManager,
 }
\ No newline at end of file
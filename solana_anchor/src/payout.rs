@@ -0,0 +1,207 @@
+use {
+    crate::{Creator, PoolError},
+    anchor_lang::prelude::ProgramError,
+};
+
+/// Split of a sale price between the marketplace, the seller and the creators.
+/// `fee + seller + creators` always adds up to the sale price.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PayoutTable {
+    /// marketplace fee
+    pub fee : u64,
+    /// seller payout
+    pub seller : u64,
+    /// creator payouts, in the same order as the creators passed in
+    pub creators : Vec<u64>,
+}
+
+/// Marketplace fee taken from `price`, rounded down.
+pub fn calculate_fee(price : u64, fee_basis_points : u16) -> Result<u64, ProgramError> {
+    let fee = (price as u128)
+        .checked_mul(fee_basis_points as u128)
+        .ok_or(PoolError::MathOverflow)?
+        / 10000u128;
+    Ok(fee as u64)
+}
+
+/// Builds the payout table for a sale of `price` of which `fee` went to the marketplace.
+///
+/// On a primary sale the creators share the whole net price, on a secondary sale they
+/// share `seller_fee_basis_points` of it and the seller gets the rest. Every creator
+/// share is rounded down. The rounding remainder goes to the seller, except on a
+/// primary sale where it goes to the first creator.
+pub fn payout_table(
+    price : u64,
+    fee : u64,
+    is_primary : bool,
+    seller_fee_basis_points : u16,
+    creators : &[Creator],
+    ) -> Result<PayoutTable, ProgramError> {
+    let net_price = price.checked_sub(fee).ok_or(PoolError::MathOverflow)?;
+    let royalty : u128 = if creators.is_empty() {
+        0
+    } else if is_primary {
+        net_price as u128
+    } else {
+        (net_price as u128)
+            .checked_mul(seller_fee_basis_points as u128)
+            .ok_or(PoolError::MathOverflow)?
+            / 10000u128
+    };
+
+    let mut creator_amounts = Vec::with_capacity(creators.len());
+    let mut distributed : u64 = 0;
+    for c in creators {
+        let amount = (royalty
+            .checked_mul(c.share as u128)
+            .ok_or(PoolError::MathOverflow)?
+            / 100u128) as u64;
+        distributed = distributed.checked_add(amount).ok_or(PoolError::MathOverflow)?;
+        creator_amounts.push(amount);
+    }
+    if distributed > net_price {
+        return Err(PoolError::InvalidRoyalty.into());
+    }
+
+    let remainder = net_price - distributed;
+    let mut seller = remainder;
+    if is_primary && !creator_amounts.is_empty() {
+        creator_amounts[0] += remainder;
+        seller = 0;
+    }
+
+    Ok(PayoutTable{
+        fee,
+        seller,
+        creators : creator_amounts,
+    })
+}
+
+/// Splits the price of a bundle into equal shares for the lead listing and each of its
+/// `item_num` items. Returns the lead share, which also carries the rounding remainder,
+/// and the share of every item.
+pub fn bundle_prices(price : u64, item_num : usize) -> (u64, u64) {
+    let item_price = price / (item_num as u64 + 1);
+    (price - item_price * item_num as u64, item_price)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        anchor_lang::prelude::Pubkey,
+    };
+
+    fn creators(shares : &[u8]) -> Vec<Creator> {
+        shares.iter().map(|share| Creator{
+            address : Pubkey::new_unique(),
+            verified : false,
+            share : *share,
+        }).collect()
+    }
+
+    #[test]
+    fn primary_sale_pays_creators_by_share() {
+        let table = payout_table(1000, 0, true, 500, &creators(&[70, 30])).unwrap();
+        assert_eq!(table, PayoutTable{ fee : 0, seller : 0, creators : vec![700, 300] });
+    }
+
+    #[test]
+    fn secondary_sale_pays_royalty_to_creators_and_rest_to_seller() {
+        let table = payout_table(10000, 0, false, 500, &creators(&[50, 50])).unwrap();
+        assert_eq!(table, PayoutTable{ fee : 0, seller : 9500, creators : vec![250, 250] });
+    }
+
+    #[test]
+    fn sale_without_creators_pays_seller() {
+        let secondary = payout_table(1000, 0, false, 500, &[]).unwrap();
+        assert_eq!(secondary, PayoutTable{ fee : 0, seller : 1000, creators : vec![] });
+        let primary = payout_table(1000, 0, true, 500, &[]).unwrap();
+        assert_eq!(primary, PayoutTable{ fee : 0, seller : 1000, creators : vec![] });
+    }
+
+    #[test]
+    fn fee_is_taken_before_royalty() {
+        let fee = calculate_fee(10000, 250).unwrap();
+        assert_eq!(fee, 250);
+
+        let secondary = payout_table(10000, fee, false, 1000, &creators(&[60, 40])).unwrap();
+        assert_eq!(secondary, PayoutTable{ fee : 250, seller : 8775, creators : vec![585, 390] });
+
+        let primary = payout_table(10000, fee, true, 1000, &creators(&[50, 50])).unwrap();
+        assert_eq!(primary, PayoutTable{ fee : 250, seller : 0, creators : vec![4875, 4875] });
+    }
+
+    #[test]
+    fn fee_above_price_fails() {
+        assert!(payout_table(100, 101, false, 0, &[]).is_err());
+    }
+
+    #[test]
+    fn creator_shares_above_hundred_fail() {
+        assert!(payout_table(1000, 0, true, 0, &creators(&[80, 80])).is_err());
+    }
+
+    fn total(table : &PayoutTable) -> u128 {
+        table.fee as u128 + table.seller as u128 + table.creators.iter().map(|c| *c as u128).sum::<u128>()
+    }
+
+    #[test]
+    fn payouts_always_sum_to_price() {
+        let prices = [0, 1, 2, 3, 7, 99, 101, 9999, 10001, u64::MAX / 3, u64::MAX - 1, u64::MAX];
+        let share_sets : [&[u8]; 5] = [&[], &[100], &[33, 33, 34], &[1, 99], &[17, 29, 31, 23]];
+        for price in prices {
+            for fee_basis_points in [0, 1, 250, 1000, 10000] {
+                let fee = calculate_fee(price, fee_basis_points).unwrap();
+                for seller_fee_basis_points in [0, 1, 333, 9999, 10000] {
+                    for shares in share_sets {
+                        for is_primary in [true, false] {
+                            let table = payout_table(price, fee, is_primary, seller_fee_basis_points, &creators(shares)).unwrap();
+                            assert_eq!(total(&table), price as u128);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fee_rounds_down() {
+        assert_eq!(calculate_fee(9999, 1).unwrap(), 0);
+        assert_eq!(calculate_fee(10001, 1).unwrap(), 1);
+        assert_eq!(calculate_fee(u64::MAX, 10000).unwrap(), u64::MAX);
+        assert_eq!(calculate_fee(u64::MAX, 1000).unwrap(), u64::MAX / 10);
+    }
+
+    #[test]
+    fn secondary_rounding_remainder_goes_to_seller() {
+        // royalty is 3 of 101, split 33/33/34 into 0, 0 and 1
+        let table = payout_table(101, 0, false, 300, &creators(&[33, 33, 34])).unwrap();
+        assert_eq!(table, PayoutTable{ fee : 0, seller : 100, creators : vec![0, 0, 1] });
+    }
+
+    #[test]
+    fn primary_rounding_remainder_goes_to_first_creator() {
+        let table = payout_table(1001, 0, true, 0, &creators(&[70, 30])).unwrap();
+        assert_eq!(table, PayoutTable{ fee : 0, seller : 0, creators : vec![701, 300] });
+    }
+
+    #[test]
+    fn one_unit_price() {
+        let secondary = payout_table(1, 0, false, 10000, &creators(&[50, 50])).unwrap();
+        assert_eq!(secondary, PayoutTable{ fee : 0, seller : 1, creators : vec![0, 0] });
+        let primary = payout_table(1, 0, true, 0, &creators(&[50, 50])).unwrap();
+        assert_eq!(primary, PayoutTable{ fee : 0, seller : 0, creators : vec![1, 0] });
+    }
+
+    #[test]
+    fn max_price_with_odd_shares() {
+        let price = u64::MAX;
+        let fee = calculate_fee(price, 1000).unwrap();
+        let table = payout_table(price, fee, false, 999, &creators(&[33, 33, 34])).unwrap();
+        assert_eq!(total(&table), price as u128);
+        let royalty = ((price - fee) as u128 * 999 / 10000) as u64;
+        assert_eq!(table.creators[0], (royalty as u128 * 33 / 100) as u64);
+        assert_eq!(table.creators[2], (royalty as u128 * 34 / 100) as u64);
+    }
+}
@@ -0,0 +1,234 @@
+use {
+    crate::PoolError,
+    anchor_lang::prelude::ProgramError,
+};
+
+/// Current price of a dutch auction.
+///
+/// The price starts at `start_price` at `started_at` and drops in equal steps every
+/// `price_drop_interval` seconds until it reaches `end_price` at `ended_at`, where it stays.
+pub fn dutch_auction_price(
+    start_price : u64,
+    end_price : u64,
+    started_at : i64,
+    ended_at : i64,
+    price_drop_interval : i64,
+    now : i64,
+    ) -> Result<u64, ProgramError> {
+    let duration = ended_at.checked_sub(started_at).ok_or(PoolError::MathOverflow)?;
+    if start_price < end_price || price_drop_interval <= 0 || duration < price_drop_interval {
+        return Err(PoolError::InvalidAuctionSchedule.into());
+    }
+    if now <= started_at {
+        return Ok(start_price);
+    }
+    let elapsed = now.checked_sub(started_at).ok_or(PoolError::MathOverflow)?;
+    let total_steps = (duration / price_drop_interval) as u128;
+    let elapsed_steps = ((elapsed / price_drop_interval) as u128).min(total_steps);
+    let drop = ((start_price - end_price) as u128)
+        .checked_mul(elapsed_steps)
+        .ok_or(PoolError::MathOverflow)?
+        / total_steps;
+    Ok(start_price - drop as u64)
+}
+
+/// Price the winner of a sealed-bid auction pays.
+///
+/// A first-price auction charges the highest bid. A second-price (Vickrey) auction charges
+/// the second highest bid, but never less than `floor_price`.
+pub fn sealed_auction_price(
+    is_second_price : bool,
+    highest_bid : u64,
+    second_bid : u64,
+    floor_price : u64,
+    ) -> u64 {
+    if is_second_price {
+        second_bid.max(floor_price).min(highest_bid)
+    } else {
+        highest_bid
+    }
+}
+
+/// Lowest bid that may outbid `current_bid` in an english auction.
+///
+/// An auction steps either by `min_increment` when it is set, or by `gap_tick_percentage`
+/// of the current bid otherwise. The bid is always strictly higher than the current bid.
+pub fn min_next_bid(
+    current_bid : u64,
+    gap_tick_percentage : u8,
+    min_increment : u64,
+    ) -> Result<u64, ProgramError> {
+    if min_increment > 0 {
+        return Ok(current_bid.checked_add(min_increment).ok_or(PoolError::MathOverflow)?);
+    }
+    let by_percentage = (current_bid as u128)
+        .checked_mul(100u128 + gap_tick_percentage as u128)
+        .ok_or(PoolError::MathOverflow)?
+        / 100u128;
+    let next_bid = by_percentage.max(current_bid as u128 + 1);
+    if next_bid > u64::MAX as u128 {
+        return Err(PoolError::MathOverflow.into());
+    }
+    Ok(next_bid as u64)
+}
+
+/// Result of a bid against the current leader of an english auction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProxyBidOutcome {
+    /// whether the new bidder takes the lead
+    pub challenger_leads : bool,
+    /// current price after the bid
+    pub price : u64,
+}
+
+/// Resolves a bid of `bid` with maximum `max_price` against a leader bidding up to `leader_max_price`.
+///
+/// The higher maximum wins and pays one increment over the other maximum, capped at its own
+/// maximum. The leader keeps the lead on a tie.
+pub fn resolve_proxy_bid(
+    leader_max_price : u64,
+    bid : u64,
+    max_price : u64,
+    gap_tick_percentage : u8,
+    min_increment : u64,
+    ) -> ProxyBidOutcome {
+    let outbid = |price : u64, cap : u64| {
+        min_next_bid(price, gap_tick_percentage, min_increment).map_or(cap, |next| next.min(cap))
+    };
+    if max_price > leader_max_price {
+        ProxyBidOutcome{
+            challenger_leads : true,
+            price : outbid(leader_max_price, max_price).max(bid),
+        }
+    } else {
+        ProxyBidOutcome{
+            challenger_leads : false,
+            price : outbid(max_price, leader_max_price),
+        }
+    }
+}
+
+/// Raises a leading `price` to `reserve_price` when the leader's maximum already covers it.
+pub fn raise_to_reserve(price : u64, max_price : u64, reserve_price : u64) -> u64 {
+    if price < reserve_price && max_price >= reserve_price {
+        reserve_price
+    } else {
+        price
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dutch_price_at_start_and_end() {
+        // 1000 down to 400 over 60 seconds in 10 second steps
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, 50).unwrap(), 1000);
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, 100).unwrap(), 1000);
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, 109).unwrap(), 1000);
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, 110).unwrap(), 900);
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, 159).unwrap(), 500);
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, 160).unwrap(), 400);
+    }
+
+    #[test]
+    fn dutch_price_stays_at_end_price_after_end() {
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, 161).unwrap(), 400);
+        assert_eq!(dutch_auction_price(1000, 400, 100, 160, 10, i64::MAX).unwrap(), 400);
+    }
+
+    #[test]
+    fn dutch_price_rounds_partial_drops_down() {
+        // 7 units over 3 steps, the price never goes below the end price
+        assert_eq!(dutch_auction_price(10, 3, 0, 30, 10, 10).unwrap(), 8);
+        assert_eq!(dutch_auction_price(10, 3, 0, 30, 10, 20).unwrap(), 6);
+        assert_eq!(dutch_auction_price(10, 3, 0, 30, 10, 30).unwrap(), 3);
+    }
+
+    #[test]
+    fn dutch_schedule_is_validated() {
+        assert!(dutch_auction_price(1000, 400, 100, 160, 0, 120).is_err());
+        assert!(dutch_auction_price(1000, 400, 100, 160, -10, 120).is_err());
+        assert!(dutch_auction_price(400, 1000, 100, 160, 10, 120).is_err());
+        assert!(dutch_auction_price(1000, 400, 100, 105, 10, 120).is_err());
+        assert!(dutch_auction_price(1000, 400, 160, 100, 10, 120).is_err());
+    }
+
+    #[test]
+    fn dutch_price_handles_extreme_inputs() {
+        assert_eq!(dutch_auction_price(u64::MAX, 0, 0, i64::MAX, 1, i64::MAX).unwrap(), 0);
+        // just under half of the i64::MAX steps have passed, the drop is rounded down
+        assert_eq!(dutch_auction_price(u64::MAX, 0, 0, i64::MAX, 1, i64::MAX / 2).unwrap(), u64::MAX / 2 + 2);
+        assert!(dutch_auction_price(1000, 400, i64::MIN, i64::MAX, 10, 0).is_err());
+        assert!(dutch_auction_price(1000, 400, -10, i64::MAX - 20, 10, i64::MAX).is_err());
+    }
+
+    #[test]
+    fn sealed_price_by_settlement_type() {
+        assert_eq!(sealed_auction_price(false, 500, 300, 100), 500);
+        assert_eq!(sealed_auction_price(true, 500, 300, 100), 300);
+        // a lone bidder pays the floor, and never more than own bid
+        assert_eq!(sealed_auction_price(true, 500, 0, 100), 100);
+        assert_eq!(sealed_auction_price(true, 500, 0, 800), 500);
+    }
+
+    #[test]
+    fn next_bid_by_percentage() {
+        assert_eq!(min_next_bid(100, 10, 0).unwrap(), 110);
+        assert_eq!(min_next_bid(5, 10, 0).unwrap(), 6);
+        assert_eq!(min_next_bid(0, 10, 0).unwrap(), 1);
+        assert_eq!(min_next_bid(100, 0, 0).unwrap(), 101);
+    }
+
+    #[test]
+    fn next_bid_by_absolute_increment() {
+        assert_eq!(min_next_bid(100, 0, 15).unwrap(), 115);
+        assert_eq!(min_next_bid(1000, 0, 15).unwrap(), 1015);
+        // the increment replaces the percentage when both are set
+        assert_eq!(min_next_bid(1000, 10, 15).unwrap(), 1015);
+    }
+
+    #[test]
+    fn next_bid_overflow_fails() {
+        assert!(min_next_bid(u64::MAX, 0, 0).is_err());
+        assert!(min_next_bid(u64::MAX - 5, 0, 10).is_err());
+        assert!(min_next_bid(u64::MAX / 2, 255, 0).is_err());
+        assert_eq!(min_next_bid(u64::MAX - 5, 0, 5).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn proxy_bid_higher_max_leads_by_one_increment() {
+        let outcome = resolve_proxy_bid(200, 150, 500, 10, 0);
+        assert_eq!(outcome, ProxyBidOutcome{ challenger_leads : true, price : 220 });
+        // the challenger pays at least own bid
+        let outcome = resolve_proxy_bid(200, 300, 500, 10, 0);
+        assert_eq!(outcome, ProxyBidOutcome{ challenger_leads : true, price : 300 });
+        // and never more than own max
+        let outcome = resolve_proxy_bid(200, 205, 210, 10, 0);
+        assert_eq!(outcome, ProxyBidOutcome{ challenger_leads : true, price : 210 });
+    }
+
+    #[test]
+    fn proxy_bid_leader_keeps_lead_on_tie_or_lower_max() {
+        let outcome = resolve_proxy_bid(500, 500, 500, 10, 0);
+        assert_eq!(outcome, ProxyBidOutcome{ challenger_leads : false, price : 500 });
+        let outcome = resolve_proxy_bid(500, 300, 300, 10, 0);
+        assert_eq!(outcome, ProxyBidOutcome{ challenger_leads : false, price : 330 });
+    }
+
+    #[test]
+    fn proxy_bid_at_max_value_does_not_overflow() {
+        let outcome = resolve_proxy_bid(u64::MAX, u64::MAX, u64::MAX, 10, 0);
+        assert_eq!(outcome, ProxyBidOutcome{ challenger_leads : false, price : u64::MAX });
+        let outcome = resolve_proxy_bid(u64::MAX - 1, u64::MAX, u64::MAX, 10, 0);
+        assert_eq!(outcome, ProxyBidOutcome{ challenger_leads : true, price : u64::MAX });
+    }
+
+    #[test]
+    fn reserve_is_reached_only_within_max() {
+        assert_eq!(raise_to_reserve(100, 500, 300), 300);
+        assert_eq!(raise_to_reserve(100, 200, 300), 100);
+        assert_eq!(raise_to_reserve(400, 500, 300), 400);
+    }
+}
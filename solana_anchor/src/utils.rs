@@ -1,13 +1,26 @@
 use {
     crate::PoolError,
     anchor_lang::{
-        prelude::{AccountInfo, ProgramResult,},
+        prelude::{AccountInfo, ProgramResult, Pubkey,},
         solana_program::{
             program::{invoke_signed, invoke},
         },
     },
 };
 
+/// ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL
+pub const ASSOCIATED_TOKEN_PROGRAM_ID : Pubkey = Pubkey::new_from_array([
+    140, 151, 37, 143, 78, 36, 137, 241, 187, 61, 16, 41, 20, 142, 13, 131,
+    11, 90, 19, 153, 218, 255, 16, 132, 4, 142, 123, 216, 219, 233, 248, 89,
+]);
+
+pub fn get_associated_token_address(wallet : &Pubkey, mint : &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), spl_token::id().as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    ).0
+}
+
 ///TokenTransferParams
 pub struct TokenTransferParams<'a: 'b, 'b> {
     /// source
@@ -149,4 +162,107 @@ pub fn spl_token_mint_to(params : TokenMintToParams<'_>) -> ProgramResult {
         &[mint,account,owner,token_program],
     );
     result.map_err(|_| PoolError::TokenMintToFailed.into())
-}
\ No newline at end of file
+}
+
+///TokenApproveParams
+pub struct TokenApproveParams<'a> {
+    /// source
+    pub source : AccountInfo<'a>,
+    /// delegate
+    pub delegate : AccountInfo<'a>,
+    /// owner
+    pub owner : AccountInfo<'a>,
+    /// token_program
+    pub token_program : AccountInfo<'a>,
+    /// amount
+    pub amount : u64,
+}
+
+#[inline(always)]
+pub fn spl_token_approve(params : TokenApproveParams<'_>) -> ProgramResult {
+    let TokenApproveParams {
+        source,
+        delegate,
+        owner,
+        token_program,
+        amount,
+    } = params;
+    let result = invoke(
+        &spl_token::instruction::approve(
+            token_program.key,
+            source.key,
+            delegate.key,
+            owner.key,
+            &[],
+            amount,
+        )?,
+        &[source,delegate,owner,token_program],
+    );
+    result.map_err(|_| PoolError::TokenApproveFailed.into())
+}
+
+///TokenRevokeParams
+pub struct TokenRevokeParams<'a> {
+    /// source
+    pub source : AccountInfo<'a>,
+    /// owner
+    pub owner : AccountInfo<'a>,
+    /// token_program
+    pub token_program : AccountInfo<'a>,
+}
+
+#[inline(always)]
+pub fn spl_token_revoke(params : TokenRevokeParams<'_>) -> ProgramResult {
+    let TokenRevokeParams {
+        source,
+        owner,
+        token_program,
+    } = params;
+    let result = invoke(
+        &spl_token::instruction::revoke(
+            token_program.key,
+            source.key,
+            owner.key,
+            &[],
+        )?,
+        &[source,owner,token_program],
+    );
+    result.map_err(|_| PoolError::TokenRevokeFailed.into())
+}
+
+///TokenCloseAccountParams
+pub struct TokenCloseAccountParams<'a: 'b, 'b> {
+    /// account
+    pub account : AccountInfo<'a>,
+    /// destination
+    pub destination : AccountInfo<'a>,
+    /// owner
+    pub owner : AccountInfo<'a>,
+    /// owner_signer_seeds
+    pub owner_signer_seeds : &'b [&'b [u8]],
+    /// token_program
+    pub token_program : AccountInfo<'a>,
+}
+
+#[inline(always)]
+pub fn spl_token_close_account(params : TokenCloseAccountParams<'_, '_>) -> ProgramResult {
+    let TokenCloseAccountParams {
+        account,
+        destination,
+        owner,
+        owner_signer_seeds,
+        token_program,
+    } = params;
+    let result = invoke_signed(
+        &spl_token::instruction::close_account(
+            token_program.key,
+            account.key,
+            destination.key,
+            owner.key,
+            &[],
+        )?,
+        &[account,destination,owner,token_program],
+        &[owner_signer_seeds],
+    );
+    result.map_err(|_| PoolError::TokenCloseAccountFailed.into())
+}